@@ -15,10 +15,111 @@ layout(std430, binding = 4) buffer circle_radius_buffer {
     float circle_radius[];
 };
 
+// shape = (kind, first vertex, vertex count, unused)
+// kind 0: segment, params = (start, end)
+// kind 1: box, params = (min, max)
+// kind 2: disc, params = (center, radius, unused)
+// kind 3: polygon, vertices taken from obstacle_vertex_buffer
+struct Obstacle {
+    vec4 shape;
+    vec4 params;
+};
+
+layout(std430, binding = 5) buffer obstacle_buffer {
+    Obstacle obstacles[];
+};
+
+layout(std430, binding = 6) buffer obstacle_vertex_buffer {
+    vec2 obstacle_vertices[];
+};
+
+// ends = (a, b), color = (rgb, unused)
+struct Bond {
+    vec4 ends;
+    vec4 color;
+};
+
+layout(std430, binding = 7) buffer bond_buffer {
+    Bond bonds[];
+};
+
+// Chemical channels, each field_size.x * field_size.y values row by row
+layout(std430, binding = 8) buffer field_buffer {
+    float field_values[];
+};
+
+layout(std430, binding = 9) buffer field_color_buffer {
+    vec4 field_colors[];
+};
+
+// shape = (center, radius, unused), color = (rgb, unused)
+struct Marker {
+    vec4 shape;
+    vec4 color;
+};
+
+layout(std430, binding = 10) buffer marker_buffer {
+    Marker markers[];
+};
+
 
 uniform vec2 offset;
 uniform float zoom;
 uniform float dt;
+uniform vec2 field_min;
+uniform vec2 field_max;
+uniform ivec2 field_size;
+
+// Obstacle positions are in world space, stretched like circle positions
+vec2 to_screen(vec2 p, float aspect_ratio)
+{
+    return vec2(p.x * aspect_ratio, p.y);
+}
+
+float segment_distance(vec2 p, vec2 a, vec2 b)
+{
+    vec2 pa = p - a;
+    vec2 ba = b - a;
+    float h = clamp(dot(pa, ba) / max(dot(ba, ba), 1e-12), 0.0, 1.0);
+    return length(pa - ba * h);
+}
+
+// Signed distance to an obstacle, negative inside
+float obstacle_distance(Obstacle obstacle, vec2 p, float aspect_ratio)
+{
+    int kind = int(obstacle.shape.x);
+    if (kind == 0) {
+        vec2 a = to_screen(obstacle.params.xy, aspect_ratio);
+        vec2 b = to_screen(obstacle.params.zw, aspect_ratio);
+        return segment_distance(p, a, b) - 0.004; // Give walls some thickness
+    } else if (kind == 1) {
+        vec2 lo = to_screen(obstacle.params.xy, aspect_ratio);
+        vec2 hi = to_screen(obstacle.params.zw, aspect_ratio);
+        vec2 d = abs(p - (lo + hi) * 0.5) - (hi - lo) * 0.5;
+        return length(max(d, 0.0)) + min(max(d.x, d.y), 0.0);
+    } else if (kind == 2) {
+        // Drawn the same way as the circles
+        return length(p - to_screen(obstacle.params.xy, aspect_ratio)) - obstacle.params.z;
+    }
+
+    int first = int(obstacle.shape.y);
+    int count = int(obstacle.shape.z);
+    if (count == 0) {
+        return 1e9;
+    }
+    float d = 1e9;
+    bool inside = false;
+    for (int i = 0; i < count; ++i) {
+        vec2 a = to_screen(obstacle_vertices[first + i], aspect_ratio);
+        vec2 b = to_screen(obstacle_vertices[first + (i + 1) % count], aspect_ratio);
+        d = min(d, segment_distance(p, a, b));
+        // Even-odd rule
+        if ((a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x)) {
+            inside = !inside;
+        }
+    }
+    return inside ? -d : d;
+}
 
 void main()
 {
@@ -56,6 +157,44 @@ void main()
         final_color.a += glow_color.a * (1.0 - final_color.a);
     }
 
+    // Bonds are drawn behind the circles they join
+    for (int i = 0; i < bonds.length(); ++i) {
+        vec2 a = to_screen(bonds[i].ends.xy, aspect_ratio);
+        vec2 b = to_screen(bonds[i].ends.zw, aspect_ratio);
+        float alpha = 1.0 - smoothstep(0.001, 0.002, segment_distance(coords, a, b));
+        final_color += vec4(bonds[i].color.rgb, 1.0) * alpha * (1.0 - final_color.a);
+    }
+
+    // Chemicals glow faintly under everything else
+    vec2 field_uv = (vec2(coords.x / aspect_ratio, coords.y) - field_min) / (field_max - field_min);
+    if (all(greaterThanEqual(field_uv, vec2(0.0))) && all(lessThan(field_uv, vec2(1.0)))) {
+        ivec2 cell = ivec2(field_uv * vec2(field_size));
+        int cells = field_size.x * field_size.y;
+        vec3 chemical = vec3(0.0);
+        // Skip channels the uploaded values don't cover
+        int channels = cells > 0 ? min(field_colors.length(), field_values.length() / cells) : 0;
+        for (int i = 0; i < channels; ++i) {
+            float value = field_values[i * cells + cell.y * field_size.x + cell.x];
+            chemical += field_colors[i].rgb * (value / (value + 1.0));
+        }
+        final_color.rgb += chemical * 0.5 * (1.0 - final_color.a);
+    }
+
+    // Emitter and sink outlines sit on top of the cells
+    for (int i = 0; i < markers.length(); ++i) {
+        vec2 center = to_screen(markers[i].shape.xy, aspect_ratio);
+        float ring = abs(length(coords - center) - markers[i].shape.z);
+        float alpha = 1.0 - smoothstep(0.001, 0.002, ring);
+        final_color = mix(final_color, vec4(markers[i].color.rgb, 1.0), alpha);
+    }
+
+    vec4 obstacle_color = vec4(0.35, 0.35, 0.4, 1.0);
+    for (int i = 0; i < obstacles.length(); ++i) {
+        if (obstacle_distance(obstacles[i], coords, aspect_ratio) < 0.0) {
+            final_color = obstacle_color;
+        }
+    }
+
     // Trails effect with blur
     float trail_decay = 0.8; // Adjust decay rate for trails
     float blur_radius = 0.5; // Adjust blur radius
@@ -0,0 +1,436 @@
+use crate::boundary::Boundary;
+use crate::brain::Neural;
+use crate::chemistry::Chemistry;
+use crate::interaction::{ForceCurve, InteractionMatrix};
+use crate::objects::Circle;
+use crate::species::{Relationship, SpeciesRegistry};
+use ahash::AHashMap;
+use glm::{dot, length, normalize, vec2, Vec2};
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Read-only view of one cell and the cells around it, handed to a
+/// `Behavior` each step.
+#[derive(Clone, Copy)]
+pub struct Neighbourhood<'a> {
+    pub cell: &'a Circle,
+    /// Every other cell within `radius` of `cell`, living or not, in the
+    /// order they are stored in the world.
+    pub neighbours: &'a [&'a Circle],
+    /// How far `cell`'s species sees: the force curve's cutoff, or further
+    /// if its behaviour's `perception` asks for it.
+    pub radius: f32,
+    pub boundary: &'a Boundary,
+    pub species: &'a SpeciesRegistry,
+    pub interactions: &'a InteractionMatrix,
+    pub force_curve: &'a ForceCurve,
+    pub chemistry: &'a Chemistry,
+}
+
+impl<'a> Neighbourhood<'a> {
+    /// Offset from `cell` to `other`, the short way round in wrap mode.
+    pub fn offset(&self, other: &Circle) -> Vec2 {
+        self.boundary.delta(self.cell.position, other.position)
+    }
+
+    /// How `cell`'s species treats `other`'s.
+    pub fn relationship(&self, other: &Circle) -> Relationship {
+        self.species
+            .get(self.cell.cell_type)
+            .map_or(Relationship::Neutral, |species| species.relationship(other.cell_type))
+    }
+}
+
+/// Decides which way a cell wants to go.
+///
+/// Behaviours are asked once per force evaluation, possibly from several
+/// threads at once, so they only get a read-only view. `rng` is a stream of
+/// the world's RNG set aside for this cell and step, which keeps runs
+/// reproducible from the seed whatever the thread count.
+pub trait Behavior: Send + Sync {
+    /// Distance the behaviour wants to see cells at. Neighbourhoods always
+    /// reach at least the force curve's cutoff.
+    fn perception(&self) -> f32 {
+        0.0
+    }
+
+    /// Steering force on `view.cell`, or `None` if it has nothing to react
+    /// to. Cells with nothing to react to wander off in a random direction.
+    fn steer(&self, view: &Neighbourhood, rng: &mut dyn RngCore) -> Option<Vec2>;
+}
+
+/// The particle-life force of the interaction matrix and the cell's genome.
+/// What every cell did before behaviours could be picked.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ParticleLife;
+
+impl Behavior for ParticleLife {
+    fn steer(&self, view: &Neighbourhood, _rng: &mut dyn RngCore) -> Option<Vec2> {
+        view.cell.interaction_force(
+            view.neighbours.iter().copied(),
+            view.interactions,
+            view.force_curve,
+            view.boundary,
+        )
+    }
+}
+
+/// Flee predators, chase food and drift towards friends, going by the
+/// species' relationships rather than the interaction matrix. Closer cells
+/// count for more.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Rules;
+
+impl Behavior for Rules {
+    fn steer(&self, view: &Neighbourhood, _rng: &mut dyn RngCore) -> Option<Vec2> {
+        react(view, false)
+    }
+}
+
+/// Pull of the living cells `view.cell`'s species has a relationship with.
+/// When `flocking`, friends and the cell's own species are left to the
+/// flock. `None` if there are none within sensing range.
+fn react(view: &Neighbourhood, flocking: bool) -> Option<Vec2> {
+    let range = view.radius.min(view.cell.genome.sensing_radius);
+    let mut force = vec2(0.0, 0.0);
+    let mut reacting = false;
+    for other in view.neighbours {
+        let relationship = view.relationship(other);
+        let ignored = match relationship {
+            Relationship::Neutral => true,
+            Relationship::Friend => flocking,
+            Relationship::Food | Relationship::Predator => {
+                flocking && other.cell_type == view.cell.cell_type
+            }
+        };
+        if ignored || !other.is_alive() {
+            continue;
+        }
+        let delta = view.offset(other);
+        let distance = length(delta);
+        if distance < range && distance != 0.0 {
+            let closeness = 1.0 - distance / range;
+            force = force + delta / distance * (relationship.attraction() * closeness);
+            reacting = true;
+        }
+    }
+    if reacting {
+        Some(force * view.force_curve.peak)
+    } else {
+        None
+    }
+}
+
+/// Reynolds flocking with cells of the same species. Cells still flee
+/// predators and chase food of other species like `Rules`, but leave friends
+/// to the flock.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Boids {
+    /// Flockmates further away than this are ignored.
+    pub radius: f32,
+    /// Width of the cone ahead of the cell it sees flockmates in, in
+    /// radians. `None` sees all the way round.
+    pub field_of_view: Option<f32>,
+    /// Push away from flockmates, harder the closer they are.
+    pub separation: f32,
+    /// Turn towards the flockmates' average heading, at full speed.
+    pub alignment: f32,
+    /// Head for the flockmates' centre.
+    pub cohesion: f32,
+    /// Weight of fleeing predators and chasing food.
+    pub reactions: f32,
+}
+
+impl Default for Boids {
+    fn default() -> Self {
+        Self {
+            radius: 0.2,
+            field_of_view: None,
+            separation: 1.5,
+            alignment: 1.0,
+            cohesion: 1.0,
+            reactions: 1.0,
+        }
+    }
+}
+
+impl Behavior for Boids {
+    fn perception(&self) -> f32 {
+        self.radius
+    }
+
+    fn steer(&self, view: &Neighbourhood, _rng: &mut dyn RngCore) -> Option<Vec2> {
+        let reactions = react(view, true).map(|force| force * self.reactions);
+        let flocking = self.flock(view);
+        match (flocking, reactions) {
+            (Some(flocking), Some(reactions)) => Some(flocking + reactions),
+            (flocking, reactions) => flocking.or(reactions),
+        }
+    }
+}
+
+impl Boids {
+    /// Whether a cell heading along `heading` sees something at `delta`.
+    /// Cells standing still see all the way round.
+    pub fn sees(&self, heading: Vec2, delta: Vec2) -> bool {
+        match self.field_of_view {
+            Some(angle) if angle < std::f32::consts::PI * 2.0 => {
+                let (speed, distance) = (length(heading), length(delta));
+                speed == 0.0 || distance == 0.0 || dot(heading, delta) >= (angle * 0.5).cos() * speed * distance
+            }
+            _ => true,
+        }
+    }
+
+    /// Separation, alignment and cohesion with the flockmates in view, or
+    /// `None` without any.
+    fn flock(&self, view: &Neighbourhood) -> Option<Vec2> {
+        let cell = view.cell;
+        let mut separation = vec2(0.0, 0.0);
+        let mut heading = direction(cell.velocity);
+        let mut centre = vec2(0.0, 0.0);
+        let mut count = 0;
+        for other in view.neighbours {
+            if other.cell_type != cell.cell_type || !other.is_alive() {
+                continue;
+            }
+            let delta = view.offset(other);
+            let distance = length(delta);
+            if distance >= self.radius || distance == 0.0 || !self.sees(cell.velocity, delta) {
+                continue;
+            }
+            separation = separation - delta / distance * (1.0 - distance / self.radius);
+            heading = heading + direction(other.velocity);
+            centre = centre + delta;
+            count += 1;
+        }
+        if count == 0 {
+            return None;
+        }
+
+        let count = count as f32;
+        // Steer from the current velocity to the one the flock wants, in
+        // units of the speed limit so the weights are comparable
+        let cruise = cell.speed_limit;
+        let alignment = if cruise > 0.0 && length(heading) > 0.0 {
+            (direction(heading) * cruise - cell.velocity) / cruise
+        } else {
+            vec2(0.0, 0.0)
+        };
+        let cohesion = centre / count / self.radius;
+        Some(
+            separation / count * self.separation
+                + alignment * self.alignment
+                + cohesion * self.cohesion,
+        )
+    }
+}
+
+/// Unit vector along `v`, or zero for a zero vector.
+fn direction(v: Vec2) -> Vec2 {
+    if length(v) > 0.0 {
+        normalize(v)
+    } else {
+        v
+    }
+}
+
+/// Random walk: keep pushing roughly the way the cell is going, turning by a
+/// random amount each step.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Wanderer {
+    pub strength: f32,
+    /// Largest turn per step, in radians.
+    pub jitter: f32,
+}
+
+impl Default for Wanderer {
+    fn default() -> Self {
+        Self {
+            strength: 0.5,
+            jitter: 0.5,
+        }
+    }
+}
+
+impl Behavior for Wanderer {
+    fn steer(&self, view: &Neighbourhood, rng: &mut dyn RngCore) -> Option<Vec2> {
+        let velocity = view.cell.velocity;
+        let jitter = self.jitter.abs();
+        let heading = if length(velocity) > 0.0 {
+            velocity.y.atan2(velocity.x) + rng.gen_range(-jitter..=jitter)
+        } else {
+            rng.gen_range(0.0..std::f32::consts::PI * 2.0)
+        };
+        Some(vec2(heading.cos(), heading.sin()) * self.strength)
+    }
+}
+
+/// Which behaviour a species' cells steer with.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub enum BehaviorKind {
+    #[default]
+    ParticleLife,
+    Rules,
+    Boids(Boids),
+    Wanderer(Wanderer),
+    /// Each cell's own `Brain`.
+    Neural(Neural),
+    /// A behaviour registered with `World::behaviors` under this name.
+    /// Scenes only store the name, and unknown names fall back to particle
+    /// life.
+    Custom(String),
+}
+
+impl BehaviorKind {
+    /// One of each built-in, with default settings.
+    pub fn builtins() -> [BehaviorKind; 5] {
+        [
+            BehaviorKind::ParticleLife,
+            BehaviorKind::Rules,
+            BehaviorKind::Boids(Boids::default()),
+            BehaviorKind::Wanderer(Wanderer::default()),
+            BehaviorKind::Neural(Neural::default()),
+        ]
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            BehaviorKind::ParticleLife => "Particle Life",
+            BehaviorKind::Rules => "Rules",
+            BehaviorKind::Boids(_) => "Boids",
+            BehaviorKind::Wanderer(_) => "Wanderer",
+            BehaviorKind::Neural(_) => "Neural",
+            BehaviorKind::Custom(name) => name,
+        }
+    }
+
+    /// Whether both pick the same behaviour, whatever their settings.
+    pub fn same_kind(&self, other: &BehaviorKind) -> bool {
+        match (self, other) {
+            (BehaviorKind::Custom(a), BehaviorKind::Custom(b)) => a == b,
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+
+    /// The behaviour itself, looking custom ones up in `custom`.
+    pub fn resolve<'a>(&'a self, custom: &'a Behaviors) -> &'a dyn Behavior {
+        match self {
+            BehaviorKind::ParticleLife => &ParticleLife,
+            BehaviorKind::Rules => &Rules,
+            BehaviorKind::Boids(boids) => boids,
+            BehaviorKind::Wanderer(wanderer) => wanderer,
+            BehaviorKind::Neural(neural) => neural,
+            BehaviorKind::Custom(name) => custom.get(name).unwrap_or(&ParticleLife),
+        }
+    }
+}
+
+/// Behaviours registered from code, by name. Code cannot be saved, so these
+/// are not part of a scene.
+#[derive(Clone, Default)]
+pub struct Behaviors {
+    registered: AHashMap<String, Arc<dyn Behavior>>,
+}
+
+impl std::fmt::Debug for Behaviors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.names()).finish()
+    }
+}
+
+impl Behaviors {
+    /// Make `behavior` available to species as `BehaviorKind::Custom(name)`,
+    /// replacing any behaviour registered under the same name.
+    pub fn register<B: Behavior + 'static>(&mut self, name: &str, behavior: B) {
+        self.registered.insert(name.to_string(), Arc::new(behavior));
+    }
+
+    pub fn unregister(&mut self, name: &str) -> bool {
+        self.registered.remove(name).is_some()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Behavior> {
+        self.registered.get(name).map(|behavior| behavior.as_ref())
+    }
+
+    /// Registered names in alphabetical order.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.registered.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use glm::vec3;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// Default world settings to build a `Neighbourhood` from by hand.
+    pub(crate) struct Surroundings {
+        pub boundary: Boundary,
+        pub species: SpeciesRegistry,
+        pub interactions: InteractionMatrix,
+        pub force_curve: ForceCurve,
+        pub chemistry: Chemistry,
+    }
+
+    impl Surroundings {
+        pub fn new() -> Self {
+            let species = SpeciesRegistry::default();
+            Self {
+                boundary: Boundary::default(),
+                interactions: species.interactions(),
+                species,
+                force_curve: ForceCurve::default(),
+                chemistry: Chemistry::default(),
+            }
+        }
+
+        pub fn view<'a>(&'a self, cell: &'a Circle, neighbours: &'a [&'a Circle]) -> Neighbourhood<'a> {
+            Neighbourhood {
+                cell,
+                neighbours,
+                radius: self.force_curve.cutoff_radius,
+                boundary: &self.boundary,
+                species: &self.species,
+                interactions: &self.interactions,
+                force_curve: &self.force_curve,
+                chemistry: &self.chemistry,
+            }
+        }
+    }
+
+    /// A cell of `cell_type` at `position`, standing still.
+    pub(crate) fn cell(cell_type: i32, position: Vec2) -> Circle {
+        Circle::new(cell_type, position, 0.01, vec3(1.0, 1.0, 1.0), 1.0)
+    }
+
+    #[test]
+    fn boids_ignore_flockmates_behind_them() {
+        let surroundings = Surroundings::new();
+        let mut me = cell(0, vec2(0.0, 0.0));
+        me.velocity = vec2(0.5, 0.0);
+        let steer = |boids: &Boids, other: Vec2| {
+            let flockmate = cell(0, other);
+            boids.steer(&surroundings.view(&me, &[&flockmate]), &mut StdRng::seed_from_u64(0))
+        };
+
+        let half = Boids {
+            field_of_view: Some(std::f32::consts::PI),
+            ..Boids::default()
+        };
+        assert!(steer(&half, vec2(-0.05, 0.0)).is_none());
+        assert!(steer(&half, vec2(-0.05, 0.06)).is_none());
+        assert!(steer(&half, vec2(0.05, 0.0)).is_some());
+        assert!(steer(&Boids::default(), vec2(-0.05, 0.0)).is_some());
+
+        // Standing still, the cell sees all the way round
+        assert!(half.sees(vec2(0.0, 0.0), vec2(-1.0, 0.0)));
+    }
+}
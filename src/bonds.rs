@@ -0,0 +1,240 @@
+use crate::lifecycle::EntityId;
+use glm::{dot, length, vec2, Vec2};
+use serde::{Deserialize, Serialize};
+
+/// Damped spring holding two cells together.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Bond {
+    pub a: EntityId,
+    pub b: EntityId,
+    /// Centre distance the spring pulls back to.
+    pub rest_length: f32,
+    /// Force per unit of stretch or compression.
+    pub stiffness: f32,
+    /// Force per unit of speed along the bond, opposing the cells moving
+    /// apart or together.
+    pub damping: f32,
+    /// The bond snaps once the spring force goes past this. 0 never breaks.
+    pub break_force: f32,
+}
+
+impl Bond {
+    pub fn connects(&self, id: EntityId) -> bool {
+        self.a == id || self.b == id
+    }
+
+    /// Whether this bond joins `a` and `b`, either way round.
+    pub fn joins(&self, a: EntityId, b: EntityId) -> bool {
+        (self.a == a && self.b == b) || (self.a == b && self.b == a)
+    }
+
+    /// Force of the spring alone when the centres are `distance` apart.
+    /// Positive pulls the cells together.
+    pub fn tension(&self, distance: f32) -> f32 {
+        self.stiffness * (distance - self.rest_length)
+    }
+
+    /// Force on `a`, where `delta` points from `a` to `b` and
+    /// `relative_velocity` is `b`'s velocity minus `a`'s. `b` feels the
+    /// opposite force.
+    pub fn force(&self, delta: Vec2, relative_velocity: Vec2) -> Vec2 {
+        let distance = length(delta);
+        if distance == 0.0 {
+            return delta;
+        }
+        let direction = delta / distance;
+        let closing_speed = dot(relative_velocity, direction);
+        direction * (self.tension(distance) + self.damping * closing_speed)
+    }
+
+    /// Whether the spring is pulling or pushing harder than it can hold.
+    pub fn breaks(&self, distance: f32) -> bool {
+        self.break_force > 0.0 && self.tension(distance).abs() > self.break_force
+    }
+}
+
+/// Per-species settings for bonds. Friendly cells of two species that both
+/// bond automatically are joined when they touch.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bonding {
+    /// Bond on contact with friends.
+    pub automatic: bool,
+    /// Most bonds one cell forms on contact. Bonds added by hand ignore it.
+    pub max_bonds: u32,
+    pub stiffness: f32,
+    pub damping: f32,
+    pub break_force: f32,
+}
+
+impl Default for Bonding {
+    fn default() -> Self {
+        Self {
+            automatic: false,
+            max_bonds: 2,
+            // Strong enough to hold cells together against the force
+            // curve's repulsion at close range
+            stiffness: 200.0,
+            damping: 2.0,
+            break_force: 10.0,
+        }
+    }
+}
+
+impl Bonding {
+    /// Bond from `a` to `b` with these spring settings.
+    pub fn bond(&self, a: EntityId, b: EntityId, rest_length: f32) -> Bond {
+        Bond {
+            a,
+            b,
+            rest_length,
+            stiffness: self.stiffness,
+            damping: self.damping,
+            break_force: self.break_force,
+        }
+    }
+
+    /// Spring settings halfway between two species, for bonds across them.
+    pub fn mix(&self, other: &Bonding) -> Bonding {
+        Bonding {
+            automatic: self.automatic && other.automatic,
+            max_bonds: self.max_bonds.min(other.max_bonds),
+            stiffness: (self.stiffness + other.stiffness) * 0.5,
+            damping: (self.damping + other.damping) * 0.5,
+            break_force: (self.break_force + other.break_force) * 0.5,
+        }
+    }
+}
+
+/// Ready-made bonded shapes the sandbox can drop into the world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Structure {
+    /// Cells in a line, each bonded to the next.
+    Chain { length: usize },
+    /// A chain closed into a loop.
+    Ring { length: usize },
+    /// A grid of cells bonded to their neighbours and across the diagonals,
+    /// so it holds its shape.
+    SoftBody { columns: usize, rows: usize },
+}
+
+impl Structure {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Structure::Chain { .. } => "Chain",
+            Structure::Ring { .. } => "Ring",
+            Structure::SoftBody { .. } => "Soft Body",
+        }
+    }
+
+    /// Cell offsets from the centre for cells `spacing` apart, and the pairs
+    /// of cell indices to bond.
+    pub fn layout(&self, spacing: f32) -> (Vec<Vec2>, Vec<(usize, usize)>) {
+        let mut offsets = Vec::new();
+        let mut links = Vec::new();
+        match *self {
+            Structure::Chain { length } => {
+                let start = -spacing * (length.max(1) - 1) as f32 * 0.5;
+                for i in 0..length {
+                    offsets.push(vec2(start + spacing * i as f32, 0.0));
+                    if i > 0 {
+                        links.push((i - 1, i));
+                    }
+                }
+            }
+            Structure::Ring { length } => {
+                // Neighbours on the circle are `spacing` apart
+                let angle = std::f32::consts::PI * 2.0 / length.max(1) as f32;
+                let radius = if length > 1 { spacing * 0.5 / (angle * 0.5).sin() } else { 0.0 };
+                for i in 0..length {
+                    let theta = angle * i as f32;
+                    offsets.push(vec2(theta.cos(), theta.sin()) * radius);
+                    if i > 0 {
+                        links.push((i - 1, i));
+                    }
+                }
+                if length > 2 {
+                    links.push((length - 1, 0));
+                }
+            }
+            Structure::SoftBody { columns, rows } => {
+                let origin = vec2(
+                    -spacing * (columns.max(1) - 1) as f32 * 0.5,
+                    -spacing * (rows.max(1) - 1) as f32 * 0.5,
+                );
+                let index = |column: usize, row: usize| row * columns + column;
+                for row in 0..rows {
+                    for column in 0..columns {
+                        offsets.push(origin + vec2(column as f32, row as f32) * spacing);
+                        if column > 0 {
+                            links.push((index(column - 1, row), index(column, row)));
+                        }
+                        if row > 0 {
+                            links.push((index(column, row - 1), index(column, row)));
+                        }
+                        if column > 0 && row > 0 {
+                            links.push((index(column - 1, row - 1), index(column, row)));
+                            links.push((index(column, row - 1), index(column - 1, row)));
+                        }
+                    }
+                }
+            }
+        }
+        (offsets, links)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spring(damping: f32) -> Bond {
+        Bond {
+            a: EntityId(1),
+            b: EntityId(2),
+            rest_length: 0.1,
+            stiffness: 100.0,
+            damping,
+            break_force: 5.0,
+        }
+    }
+
+    #[test]
+    fn springs_pull_back_to_rest_and_snap_past_the_break_force() {
+        let bond = spring(0.0);
+        let stretched = bond.force(vec2(0.12, 0.0), vec2(0.0, 0.0));
+        assert!((stretched.x - 2.0).abs() < 1e-4 && stretched.y == 0.0, "{:?}", stretched);
+        assert!(bond.force(vec2(0.0, 0.08), vec2(0.0, 0.0)).y < 0.0);
+
+        assert!(!bond.breaks(0.149));
+        assert!(bond.breaks(0.151));
+        assert!(bond.breaks(0.049));
+        assert!(!Bond { break_force: 0.0, ..bond }.breaks(10.0));
+    }
+
+    #[test]
+    fn damping_takes_the_swing_out_of_a_spring() {
+        // Largest stretch after a while of two unit masses bouncing on the
+        // spring, starting from 0.05 past the rest length
+        let swing = |bond: Bond| {
+            let (mut a, mut b) = (vec2(0.0, 0.0), vec2(0.15, 0.0));
+            let (mut va, mut vb) = (vec2(0.0, 0.0), vec2(0.0, 0.0));
+            let mut largest: f32 = 0.0;
+            for step in 0..3000 {
+                let force = bond.force(b - a, vb - va);
+                va = va + force * 0.001;
+                vb = vb - force * 0.001;
+                a = a + va * 0.001;
+                b = b + vb * 0.001;
+                if step >= 2000 {
+                    largest = largest.max((length(b - a) - bond.rest_length).abs());
+                }
+            }
+            largest
+        };
+        let undamped = swing(spring(0.0));
+        let damped = swing(spring(2.0));
+        assert!(undamped > 0.04, "{}", undamped);
+        assert!(damped < undamped * 0.1, "{} vs {}", damped, undamped);
+    }
+}
@@ -0,0 +1,179 @@
+use crate::objects::Circle;
+use glm::{vec2, Vec2};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum BoundaryMode {
+    /// Cells bounce off the edges of the box.
+    #[default]
+    Walls,
+    /// Leaving through one edge re-enters through the opposite one, and
+    /// distances are measured the short way round (minimum image).
+    Wrap,
+    /// No edges at all.
+    Open,
+}
+
+/// The box the world lives in and what happens at its edges.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Boundary {
+    pub mode: BoundaryMode,
+    #[serde(with = "crate::scene::vec2_serde")]
+    pub min: Vec2,
+    #[serde(with = "crate::scene::vec2_serde")]
+    pub max: Vec2,
+    /// Fraction of the normal speed kept when bouncing off a wall.
+    pub restitution: f32,
+    /// In open mode, cells further than this outside the box are removed.
+    /// Zero keeps them however far they drift.
+    pub cull_distance: f32,
+}
+
+impl Default for Boundary {
+    fn default() -> Self {
+        Self {
+            mode: BoundaryMode::default(),
+            min: vec2(-1.0, -1.0),
+            max: vec2(1.0, 1.0),
+            restitution: 0.5,
+            cull_distance: 0.0,
+        }
+    }
+}
+
+impl Boundary {
+    pub fn size(&self) -> Vec2 {
+        self.max - self.min
+    }
+
+    /// Vector from `from` to `to`. In wrap mode this is the shortest such
+    /// vector across any seam.
+    pub fn delta(&self, from: Vec2, to: Vec2) -> Vec2 {
+        let mut delta = to - from;
+        if self.mode == BoundaryMode::Wrap {
+            let size = self.size();
+            if size.x > 0.0 {
+                delta.x -= size.x * (delta.x / size.x).round();
+            }
+            if size.y > 0.0 {
+                delta.y -= size.y * (delta.y / size.y).round();
+            }
+        }
+        delta
+    }
+
+    /// Whether a cell at `position` has drifted far enough out of an open
+    /// world to be removed.
+    pub fn culls(&self, position: Vec2) -> bool {
+        if self.mode != BoundaryMode::Open || self.cull_distance <= 0.0 {
+            return false;
+        }
+        let reach = self.cull_distance;
+        position.x < self.min.x - reach
+            || position.x > self.max.x + reach
+            || position.y < self.min.y - reach
+            || position.y > self.max.y + reach
+    }
+
+    /// Bring a circle that left the box back in.
+    pub fn apply(&self, object: &mut Circle) {
+        match self.mode {
+            BoundaryMode::Walls => {
+                let low = self.min + object.radius;
+                let high = self.max - object.radius;
+                if object.position.x < low.x {
+                    object.position.x = low.x;
+                    object.velocity.x = object.velocity.x.abs() * self.restitution;
+                } else if object.position.x > high.x {
+                    object.position.x = high.x;
+                    object.velocity.x = -object.velocity.x.abs() * self.restitution;
+                }
+                if object.position.y < low.y {
+                    object.position.y = low.y;
+                    object.velocity.y = object.velocity.y.abs() * self.restitution;
+                } else if object.position.y > high.y {
+                    object.position.y = high.y;
+                    object.velocity.y = -object.velocity.y.abs() * self.restitution;
+                }
+            }
+            BoundaryMode::Wrap => {
+                let size = self.size();
+                if size.x > 0.0 {
+                    object.position.x = self.min.x + (object.position.x - self.min.x).rem_euclid(size.x);
+                }
+                if size.y > 0.0 {
+                    object.position.y = self.min.y + (object.position.y - self.min.y).rem_euclid(size.y);
+                }
+            }
+            BoundaryMode::Open => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glm::vec3;
+
+    fn wrapped() -> Boundary {
+        Boundary {
+            mode: BoundaryMode::Wrap,
+            ..Boundary::default()
+        }
+    }
+
+    #[test]
+    fn wrap_measures_the_short_way_round() {
+        let boundary = wrapped();
+        let delta = boundary.delta(vec2(0.9, -0.95), vec2(-0.9, 0.95));
+        assert!((delta.x - 0.2).abs() < 1e-6 && (delta.y + 0.1).abs() < 1e-6, "{:?}", delta);
+
+        let walls = Boundary::default();
+        assert_eq!(walls.delta(vec2(0.9, 0.0), vec2(-0.9, 0.0)), vec2(-1.8, 0.0));
+    }
+
+    #[test]
+    fn cells_wrap_bounce_or_leave() {
+        let mut cell = Circle::new(0, vec2(1.25, -1.5), 0.1, vec3(1.0, 1.0, 1.0), 1.0);
+        cell.velocity = vec2(1.0, -1.0);
+
+        let mut wrapping = cell.clone();
+        wrapped().apply(&mut wrapping);
+        assert!((wrapping.position.x + 0.75).abs() < 1e-6 && (wrapping.position.y - 0.5).abs() < 1e-6);
+        assert_eq!(wrapping.velocity, cell.velocity);
+
+        let mut bouncing = cell.clone();
+        Boundary::default().apply(&mut bouncing);
+        assert_eq!(bouncing.position, vec2(0.9, -0.9));
+        assert_eq!(bouncing.velocity, vec2(-0.5, 0.5));
+
+        let mut leaving = cell.clone();
+        let open = Boundary {
+            mode: BoundaryMode::Open,
+            ..Boundary::default()
+        };
+        open.apply(&mut leaving);
+        assert_eq!(leaving, cell);
+    }
+
+    #[test]
+    fn open_worlds_cull_only_past_the_cull_distance() {
+        let mut open = Boundary {
+            mode: BoundaryMode::Open,
+            ..Boundary::default()
+        };
+        assert!(!open.culls(vec2(100.0, 0.0)));
+
+        open.cull_distance = 0.5;
+        assert!(!open.culls(vec2(1.4, -1.4)));
+        assert!(open.culls(vec2(1.6, 0.0)));
+        assert!(open.culls(vec2(0.0, -1.6)));
+
+        let walls = Boundary {
+            cull_distance: 0.5,
+            ..Boundary::default()
+        };
+        assert!(!walls.culls(vec2(1.6, 0.0)));
+    }
+}
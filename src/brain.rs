@@ -0,0 +1,284 @@
+use crate::behavior::{Behavior, Neighbourhood};
+use crate::species::Relationship;
+use glm::{dot, length, normalize, vec2, Vec2};
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+/// Chemical channels a brain senses, the world's first ones.
+pub const CHEMICAL_SENSES: usize = 2;
+
+/// Number of sensor inputs, including the constant bias.
+pub const INPUTS: usize = 16;
+
+/// Number of outputs: thrust and turn.
+pub const OUTPUTS: usize = 2;
+
+/// Short names of the sensor inputs, in order. Directions are split into
+/// how far ahead and how far to the left of the cell's heading they point.
+pub const SENSORS: [&str; INPUTS] = [
+    "food ahead",
+    "food left",
+    "food near",
+    "predator ahead",
+    "predator left",
+    "predator near",
+    "friend ahead",
+    "friend left",
+    "friend near",
+    "energy",
+    "density",
+    "chemical 1 ahead",
+    "chemical 1 left",
+    "chemical 2 ahead",
+    "chemical 2 left",
+    "bias",
+];
+
+/// Short names of the outputs, in order.
+pub const ACTIONS: [&str; OUTPUTS] = ["thrust", "turn"];
+
+/// Small feed-forward network steering one cell: the sensor inputs, one
+/// hidden layer and the outputs, all squashed with `tanh`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Brain {
+    hidden: usize,
+    /// Input to hidden weights row by row, one row per hidden neuron, then
+    /// hidden to output weights the same way. Missing weights, say from a
+    /// hand-edited scene, count as zero.
+    weights: Vec<f32>,
+}
+
+/// Every neuron's value for one set of inputs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Activations {
+    pub inputs: [f32; INPUTS],
+    pub hidden: Vec<f32>,
+    pub outputs: [f32; OUTPUTS],
+}
+
+impl Brain {
+    /// A brain with `hidden` hidden neurons and weights drawn uniformly from
+    /// [-1, 1].
+    pub fn random<R: Rng>(hidden: usize, rng: &mut R) -> Self {
+        let count = hidden * (INPUTS + OUTPUTS);
+        Self {
+            hidden,
+            weights: (0..count).map(|_| rng.gen_range(-1.0..=1.0)).collect(),
+        }
+    }
+
+    pub fn hidden(&self) -> usize {
+        self.hidden
+    }
+
+    pub fn weights(&self) -> &[f32] {
+        &self.weights
+    }
+
+    /// Weight from input `input` to hidden neuron `neuron`.
+    pub fn input_weight(&self, neuron: usize, input: usize) -> f32 {
+        self.weights.get(neuron * INPUTS + input).copied().unwrap_or(0.0)
+    }
+
+    /// Weight from hidden neuron `neuron` to output `output`.
+    pub fn output_weight(&self, output: usize, neuron: usize) -> f32 {
+        let index = self.hidden * INPUTS + output * self.hidden + neuron;
+        self.weights.get(index).copied().unwrap_or(0.0)
+    }
+
+    pub fn think(&self, inputs: [f32; INPUTS]) -> Activations {
+        let hidden: Vec<f32> = (0..self.hidden)
+            .map(|neuron| {
+                let sum: f32 = inputs
+                    .iter()
+                    .enumerate()
+                    .map(|(input, x)| self.input_weight(neuron, input) * x)
+                    .sum();
+                sum.tanh()
+            })
+            .collect();
+        let mut outputs = [0.0; OUTPUTS];
+        for (output, value) in outputs.iter_mut().enumerate() {
+            let sum: f32 = hidden
+                .iter()
+                .enumerate()
+                .map(|(neuron, h)| self.output_weight(output, neuron) * h)
+                .sum();
+            *value = sum.tanh();
+        }
+        Activations { inputs, hidden, outputs }
+    }
+
+    /// Nudge each weight with chance `rate` by up to `scale`, keeping it in
+    /// [-1, 1].
+    pub fn mutate<R: Rng>(&mut self, rate: f32, scale: f32, rng: &mut R) {
+        for weight in &mut self.weights {
+            if rng.gen::<f32>() < rate {
+                *weight = (*weight + rng.gen_range(-1.0..=1.0) * scale).clamp(-1.0, 1.0);
+            }
+        }
+    }
+}
+
+/// What a cell's sensors read in `view`, in the order of `SENSORS`.
+pub fn sense(view: &Neighbourhood) -> [f32; INPUTS] {
+    let cell = view.cell;
+    let (ahead, left) = frame(cell.velocity);
+    let range = view.radius.min(cell.genome.sensing_radius);
+    let mut inputs = [0.0; INPUTS];
+
+    // Nearest living cell of each kind, and how many living cells are in range
+    let kinds = [Relationship::Food, Relationship::Predator, Relationship::Friend];
+    let mut nearest: [Option<(f32, Vec2)>; 3] = [None; 3];
+    let mut crowd = 0;
+    for other in view.neighbours.iter().filter(|other| other.is_alive()) {
+        let delta = view.offset(other);
+        let distance = length(delta);
+        if distance >= range {
+            continue;
+        }
+        crowd += 1;
+        let relationship = view.relationship(other);
+        let kind = match kinds.iter().position(|&kind| kind == relationship) {
+            Some(kind) => kind,
+            None => continue,
+        };
+        if nearest[kind].map_or(true, |(closest, _)| distance < closest) {
+            nearest[kind] = Some((distance, delta));
+        }
+    }
+    for (kind, nearest) in nearest.iter().enumerate() {
+        if let Some((distance, delta)) = *nearest {
+            let direction = if distance > 0.0 { delta / distance } else { delta };
+            inputs[kind * 3] = dot(direction, ahead);
+            inputs[kind * 3 + 1] = dot(direction, left);
+            inputs[kind * 3 + 2] = 1.0 - distance / range;
+        }
+    }
+
+    inputs[9] = cell.energy.max(0.0) / (cell.energy.max(0.0) + 100.0);
+    let crowd = crowd as f32;
+    inputs[10] = crowd / (crowd + 10.0);
+
+    let channels = view.chemistry.channels().len().min(CHEMICAL_SENSES);
+    for channel in 0..channels {
+        let gradient = view.chemistry.gradient(channel, cell.position, view.boundary);
+        let gradient = gradient / (1.0 + length(gradient));
+        inputs[11 + channel * 2] = dot(gradient, ahead);
+        inputs[12 + channel * 2] = dot(gradient, left);
+    }
+
+    inputs[INPUTS - 1] = 1.0;
+    inputs
+}
+
+/// Unit vectors ahead of and to the left of a cell moving with `velocity`.
+/// Cells standing still face along +x.
+fn frame(velocity: Vec2) -> (Vec2, Vec2) {
+    let ahead = if length(velocity) > 0.0 { normalize(velocity) } else { vec2(1.0, 0.0) };
+    (ahead, vec2(-ahead.y, ahead.x))
+}
+
+/// Steers each cell with its own `Brain`. Cells without one wander.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Neural {
+    /// Force at full thrust or a full turn.
+    pub strength: f32,
+    /// Hidden neurons of the brains given to new cells of the species.
+    pub hidden: usize,
+}
+
+impl Default for Neural {
+    fn default() -> Self {
+        Self {
+            strength: 1.0,
+            hidden: 8,
+        }
+    }
+}
+
+impl Behavior for Neural {
+    fn steer(&self, view: &Neighbourhood, _rng: &mut dyn RngCore) -> Option<Vec2> {
+        let brain = view.cell.brain.as_ref()?;
+        let [thrust, turn] = brain.think(sense(view)).outputs;
+        let (ahead, left) = frame(view.cell.velocity);
+        Some((ahead * thrust + left * turn) * self.strength)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::behavior::tests::{cell, Surroundings};
+    use crate::genetics::Reproduction;
+    use crate::lifecycle::LifeStage;
+    use crate::objects::Circle;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn sensors_and_outputs_fit_the_network() {
+        let brain = Brain::random(5, &mut StdRng::seed_from_u64(1));
+        assert_eq!(brain.weights().len(), 5 * (INPUTS + OUTPUTS));
+        assert_eq!(SENSORS.len(), INPUTS);
+        assert_eq!(ACTIONS.len(), OUTPUTS);
+
+        let surroundings = Surroundings::new();
+        let mut me = cell(0, vec2(0.0, 0.0));
+        me.velocity = vec2(0.0, 1.0);
+        // Food for red, straight ahead
+        let food = cell(4, vec2(0.0, 0.1));
+        let inputs = sense(&surroundings.view(&me, &[&food]));
+        assert!((inputs[0] - 1.0).abs() < 1e-6 && inputs[1].abs() < 1e-6);
+        assert_eq!(inputs[INPUTS - 1], 1.0);
+
+        let activations = brain.think(inputs);
+        assert_eq!(activations.hidden.len(), 5);
+        assert!(activations.outputs.iter().all(|output| output.abs() < 1.0));
+    }
+
+    #[test]
+    fn density_counts_living_cells_in_sensing_range() {
+        let surroundings = Surroundings::new();
+        let mut me = cell(0, vec2(0.0, 0.0));
+        me.genome.sensing_radius = 0.2;
+        let near = cell(1, vec2(0.1, 0.0));
+        let mut dead = cell(1, vec2(0.0, 0.1));
+        dead.life = LifeStage::Dead;
+        let far = cell(1, vec2(0.3, 0.0));
+
+        let density = |neighbours: &[&Circle]| sense(&surroundings.view(&me, neighbours))[10];
+        let one = density(&[&near]);
+        assert!((one - 1.0 / 11.0).abs() < 1e-6);
+        assert_eq!(density(&[&near, &dead, &far]), one);
+    }
+
+    #[test]
+    fn children_inherit_their_parents_brain_with_small_changes() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut parent = cell(0, vec2(0.0, 0.0));
+        parent.brain = Some(Brain::random(4, &mut rng));
+        let parent_weights = parent.brain.as_ref().unwrap().weights().to_vec();
+
+        let faithful = Reproduction {
+            mutation_rate: 0.0,
+            ..Reproduction::default()
+        };
+        let mut child = parent.clone();
+        faithful.mutate(&mut child, 1, &mut rng);
+        assert_eq!(child.brain, parent.brain);
+
+        let sloppy = Reproduction {
+            mutation_rate: 1.0,
+            ..Reproduction::default()
+        };
+        let mut child = parent.clone();
+        sloppy.mutate(&mut child, 1, &mut rng);
+        let weights = child.brain.as_ref().unwrap().weights();
+        assert_ne!(weights, &parent_weights[..]);
+        for (weight, parent) in weights.iter().zip(&parent_weights) {
+            assert!((weight - parent).abs() <= sloppy.mutation_scale + 1e-6);
+            assert!(weight.abs() <= 1.0);
+        }
+    }
+}
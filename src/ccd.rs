@@ -0,0 +1,147 @@
+use crate::boundary::Boundary;
+use crate::obstacles::Obstacle;
+use glm::{dot, length, vec2, Vec2};
+
+/// Gaps smaller than this fraction of a circle's radius count as touching.
+/// Touching pairs are left to the overlap pass, so a contact that was just
+/// resolved is not found again at the same instant.
+const CONTACT_SLOP: f32 = 1e-3;
+
+/// Conservative advancement steps tried against one obstacle before giving up.
+const MAX_ADVANCE_ITERATIONS: u32 = 32;
+
+/// Fraction of `motion` after which two circles `min_distance` apart at their
+/// centres first touch, if they do within it. `delta` is the offset from the
+/// second circle to the first and `motion` the first's movement relative to
+/// the second. Circles already touching or overlapping are not reported.
+pub fn circles(delta: Vec2, motion: Vec2, min_distance: f32) -> Option<f32> {
+    let gap = length(delta) - min_distance;
+    if gap <= min_distance * CONTACT_SLOP {
+        return None;
+    }
+
+    // |delta + motion * t| = min_distance
+    let a = dot(motion, motion);
+    let half_b = dot(delta, motion);
+    if a == 0.0 || half_b >= 0.0 {
+        return None;
+    }
+    let c = dot(delta, delta) - min_distance * min_distance;
+    let discriminant = half_b * half_b - a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let t = (-half_b - discriminant.sqrt()) / a;
+    if t <= 1.0 {
+        Some(t.max(0.0))
+    } else {
+        None
+    }
+}
+
+/// First wall of the box a circle moving by `motion` hits, as the fraction
+/// of `motion` and the wall's inward normal. Only meaningful in walls mode.
+pub fn walls(boundary: &Boundary, position: Vec2, radius: f32, motion: Vec2) -> Option<(f32, Vec2)> {
+    let low = boundary.min + radius;
+    let high = boundary.max - radius;
+    let slop = radius * CONTACT_SLOP;
+    let axis = |position: f32, motion: f32, low: f32, high: f32| {
+        if motion < 0.0 && position - low > slop {
+            Some(((low - position) / motion, 1.0))
+        } else if motion > 0.0 && high - position > slop {
+            Some(((high - position) / motion, -1.0))
+        } else {
+            None
+        }
+    };
+
+    let x = axis(position.x, motion.x, low.x, high.x).map(|(t, sign)| (t, vec2(sign, 0.0)));
+    let y = axis(position.y, motion.y, low.y, high.y).map(|(t, sign)| (t, vec2(0.0, sign)));
+    x.into_iter()
+        .chain(y)
+        .filter(|&(t, _)| t <= 1.0)
+        .fold(None, earliest)
+}
+
+/// First contact between a circle moving by `motion` and `obstacle`, as the
+/// fraction of `motion` and the obstacle's outward normal there.
+///
+/// Found by conservative advancement: the circle is moved forward by its
+/// current distance to the outline, which can never overshoot the surface.
+pub fn obstacle(obstacle: &Obstacle, position: Vec2, radius: f32, motion: Vec2) -> Option<(f32, Vec2)> {
+    let travel = length(motion);
+    if travel == 0.0 {
+        return None;
+    }
+    let slop = radius * CONTACT_SLOP;
+    let (distance, _) = obstacle.signed_distance(position)?;
+    if distance - radius <= slop {
+        return None;
+    }
+
+    let mut t = 0.0;
+    for _ in 0..MAX_ADVANCE_ITERATIONS {
+        let (distance, normal) = obstacle.signed_distance(position + motion * t)?;
+        let gap = distance - radius;
+        if gap <= slop {
+            // Grazing contacts do not change the course
+            return if dot(motion, normal) < 0.0 { Some((t, normal)) } else { None };
+        }
+        t += gap / travel;
+        if t > 1.0 {
+            return None;
+        }
+    }
+    None
+}
+
+/// `velocity` after bouncing off a surface with outward `normal`, keeping
+/// `restitution` of the speed along the normal.
+pub fn reflect(velocity: Vec2, normal: Vec2, restitution: f32) -> Vec2 {
+    let velocity_along_normal = dot(velocity, normal);
+    if velocity_along_normal < 0.0 {
+        velocity - normal * ((1.0 + restitution) * velocity_along_normal)
+    } else {
+        velocity
+    }
+}
+
+/// Fold step keeping whichever impact comes first.
+pub fn earliest<T>(best: Option<(f32, T)>, candidate: (f32, T)) -> Option<(f32, T)> {
+    match best {
+        Some(best) if best.0 <= candidate.0 => Some(best),
+        _ => Some(candidate),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn head_on_circles_meet_part_way() {
+        let t = circles(vec2(1.0, 0.0), vec2(-2.0, 0.0), 0.5).unwrap();
+        assert!((t - 0.25).abs() < 1e-6, "{}", t);
+        // Too far to close the gap within the step
+        assert_eq!(circles(vec2(3.0, 0.0), vec2(-2.0, 0.0), 0.5), None);
+    }
+
+    #[test]
+    fn grazing_circles_miss() {
+        assert_eq!(circles(vec2(1.0, 0.6), vec2(-2.0, 0.0), 0.5), None);
+        assert!(circles(vec2(1.0, 0.4), vec2(-2.0, 0.0), 0.5).is_some());
+    }
+
+    #[test]
+    fn overlapping_circles_are_left_to_the_overlap_pass() {
+        assert_eq!(circles(vec2(0.3, 0.0), vec2(-2.0, 0.0), 0.5), None);
+        assert_eq!(circles(vec2(0.5, 0.0), vec2(-2.0, 0.0), 0.5), None);
+    }
+
+    #[test]
+    fn circles_without_relative_motion_never_meet() {
+        assert_eq!(circles(vec2(1.0, 0.0), vec2(0.0, 0.0), 0.5), None);
+        // Moving apart
+        assert_eq!(circles(vec2(1.0, 0.0), vec2(2.0, 0.0), 0.5), None);
+    }
+}
@@ -0,0 +1,360 @@
+use crate::boundary::{Boundary, BoundaryMode};
+use crate::objects::Circle;
+use crate::species::SpeciesRegistry;
+use glm::{length, vec2, vec3, Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+/// How a species takes part in one chemical channel.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChemicalResponse {
+    /// Amount each living cell deposits per second where it stands.
+    pub emit: f32,
+    /// Pull up the channel's gradient. Negative values push cells down it.
+    pub taxis: f32,
+}
+
+/// One chemical, stored as a concentration per grid cell.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Channel {
+    pub name: String,
+    #[serde(with = "crate::scene::vec3_serde")]
+    pub color: Vec3, // RGB, 0-255 like `Circle::color`
+    /// How fast the chemical spreads to neighbouring grid cells, per second.
+    pub diffusion: f32,
+    /// Fraction of the chemical that fades per second.
+    pub evaporation: f32,
+    values: Vec<f32>,
+}
+
+impl Default for Channel {
+    fn default() -> Self {
+        Self::new("Pheromone", vec3(255.0, 255.0, 255.0))
+    }
+}
+
+impl Channel {
+    pub fn new(name: &str, color: Vec3) -> Self {
+        Self {
+            name: name.to_string(),
+            color,
+            diffusion: 2.0,
+            evaporation: 0.2,
+            values: Vec::new(),
+        }
+    }
+
+    /// Concentrations row by row, starting at the boundary's minimum corner.
+    pub fn values(&self) -> &[f32] {
+        &self.values
+    }
+
+    pub fn clear(&mut self) {
+        self.values.iter_mut().for_each(|value| *value = 0.0);
+    }
+}
+
+/// Scalar grids covering the world's boundary box, one per chemical channel.
+/// Cells deposit into them, and each step they diffuse and evaporate.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "ChemistryFile")]
+pub struct Chemistry {
+    columns: usize,
+    rows: usize,
+    channels: Vec<Channel>,
+    #[serde(skip)]
+    scratch: Vec<f32>,
+}
+
+impl Default for Chemistry {
+    fn default() -> Self {
+        Self {
+            columns: 64,
+            rows: 64,
+            channels: Vec::new(),
+            scratch: Vec::new(),
+        }
+    }
+}
+
+/// `Chemistry` as read from a file, before the grid is checked.
+#[derive(Deserialize)]
+#[serde(default)]
+struct ChemistryFile {
+    columns: usize,
+    rows: usize,
+    channels: Vec<Channel>,
+}
+
+impl Default for ChemistryFile {
+    fn default() -> Self {
+        let Chemistry { columns, rows, channels, .. } = Chemistry::default();
+        Self { columns, rows, channels }
+    }
+}
+
+impl TryFrom<ChemistryFile> for Chemistry {
+    type Error = String;
+
+    fn try_from(file: ChemistryFile) -> Result<Self, Self::Error> {
+        let size = match file.columns.checked_mul(file.rows) {
+            Some(size) if size > 0 => size,
+            _ => return Err(format!("chemistry grid of {}x{} cells", file.columns, file.rows)),
+        };
+        for channel in &file.channels {
+            if channel.values.len() != size {
+                return Err(format!(
+                    "chemical channel \"{}\" has {} values for {} grid cells",
+                    channel.name,
+                    channel.values.len(),
+                    size
+                ));
+            }
+        }
+        Ok(Self {
+            columns: file.columns,
+            rows: file.rows,
+            channels: file.channels,
+            scratch: Vec::new(),
+        })
+    }
+}
+
+impl Chemistry {
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Change the grid size. Channels that no longer fit are emptied.
+    pub fn set_resolution(&mut self, columns: usize, rows: usize) {
+        self.columns = columns.max(1);
+        self.rows = rows.max(1);
+        let size = self.columns * self.rows;
+        for channel in &mut self.channels {
+            if channel.values.len() != size {
+                channel.values = vec![0.0; size];
+            }
+        }
+    }
+
+    pub fn channels(&self) -> &[Channel] {
+        &self.channels
+    }
+
+    pub fn channels_mut(&mut self) -> &mut [Channel] {
+        &mut self.channels
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.channels.is_empty()
+    }
+
+    /// Add an empty channel and return its index.
+    pub fn add(&mut self, mut channel: Channel) -> usize {
+        channel.values = vec![0.0; self.columns * self.rows];
+        self.channels.push(channel);
+        self.channels.len() - 1
+    }
+
+    /// Remove a channel. Later channels move down one index, see
+    /// `World::remove_channel` for keeping species rules in step.
+    pub fn remove(&mut self, channel: usize) -> Option<Channel> {
+        if channel < self.channels.len() {
+            Some(self.channels.remove(channel))
+        } else {
+            None
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.channels.iter_mut().for_each(Channel::clear);
+    }
+
+    /// Concentration of `channel` at `position`, interpolated between the
+    /// centres of the surrounding grid cells.
+    pub fn sample(&self, channel: usize, position: Vec2, boundary: &Boundary) -> f32 {
+        let values = match self.channels.get(channel) {
+            Some(channel) if channel.values.len() == self.columns * self.rows => &channel.values,
+            _ => return 0.0,
+        };
+        let cell = self.cell_size(boundary);
+        let x = (position.x - boundary.min.x) / cell.x - 0.5;
+        let y = (position.y - boundary.min.y) / cell.y - 0.5;
+        let (column, row) = (x.floor(), y.floor());
+        let (fx, fy) = (x - column, y - row);
+        let wrap = boundary.mode == BoundaryMode::Wrap;
+        let at = |dx: isize, dy: isize| {
+            values[self.index(column as isize + dx, row as isize + dy, wrap)]
+        };
+        let bottom = at(0, 0) * (1.0 - fx) + at(1, 0) * fx;
+        let top = at(0, 1) * (1.0 - fx) + at(1, 1) * fx;
+        bottom * (1.0 - fy) + top * fy
+    }
+
+    /// Direction and steepness of the rise of `channel` at `position`.
+    pub fn gradient(&self, channel: usize, position: Vec2, boundary: &Boundary) -> Vec2 {
+        let cell = self.cell_size(boundary);
+        let dx = vec2(cell.x, 0.0);
+        let dy = vec2(0.0, cell.y);
+        vec2(
+            (self.sample(channel, position + dx, boundary)
+                - self.sample(channel, position - dx, boundary))
+                / (2.0 * cell.x),
+            (self.sample(channel, position + dy, boundary)
+                - self.sample(channel, position - dy, boundary))
+                / (2.0 * cell.y),
+        )
+    }
+
+    /// Pull of every channel on a cell of `object`'s species. Each channel
+    /// pulls with up to its `taxis` strength, less on shallow gradients.
+    pub fn taxis(&self, object: &Circle, species: &SpeciesRegistry, boundary: &Boundary) -> Vec2 {
+        let mut force = vec2(0.0, 0.0);
+        let species = match species.get(object.cell_type) {
+            Some(species) => species,
+            None => return force,
+        };
+        for (channel, response) in species.chemicals.iter().enumerate().take(self.channels.len()) {
+            if response.taxis != 0.0 {
+                let gradient = self.gradient(channel, object.position, boundary);
+                force = force + gradient * (response.taxis / (1.0 + length(gradient)));
+            }
+        }
+        force
+    }
+
+    /// Add what every living cell emits over `dt`, then let the channels
+    /// diffuse and evaporate.
+    pub fn update(
+        &mut self,
+        objects: &[Circle],
+        species: &SpeciesRegistry,
+        boundary: &Boundary,
+        dt: f32,
+    ) {
+        if self.channels.is_empty() {
+            return;
+        }
+        self.set_resolution(self.columns, self.rows);
+        let wrap = boundary.mode == BoundaryMode::Wrap;
+        let cell = self.cell_size(boundary);
+
+        for object in objects.iter().filter(|object| object.is_alive()) {
+            let species = match species.get(object.cell_type) {
+                Some(species) => species,
+                None => continue,
+            };
+            let column = ((object.position.x - boundary.min.x) / cell.x).floor() as isize;
+            let row = ((object.position.y - boundary.min.y) / cell.y).floor() as isize;
+            let index = self.index(column, row, wrap);
+            for (channel, response) in self.channels.iter_mut().zip(&species.chemicals) {
+                channel.values[index] = (channel.values[index] + response.emit * dt).max(0.0);
+            }
+        }
+
+        let (columns, rows) = (self.columns as isize, self.rows as isize);
+        let mut scratch = std::mem::take(&mut self.scratch);
+        for channel in 0..self.channels.len() {
+            let Channel { diffusion, evaporation, .. } = self.channels[channel];
+            let mix = (diffusion * dt).clamp(0.0, 1.0);
+            let keep = (-evaporation.max(0.0) * dt).exp();
+            scratch.clear();
+            scratch.extend_from_slice(&self.channels[channel].values);
+            let values = &mut self.channels[channel].values;
+            for row in 0..rows {
+                for column in 0..columns {
+                    let at = |dx: isize, dy: isize| {
+                        scratch[grid_index(column + dx, row + dy, columns, rows, wrap)]
+                    };
+                    let neighbours = (at(-1, 0) + at(1, 0) + at(0, -1) + at(0, 1)) * 0.25;
+                    let value = at(0, 0);
+                    values[(row * columns + column) as usize] = (value + (neighbours - value) * mix) * keep;
+                }
+            }
+        }
+        self.scratch = scratch;
+    }
+
+    fn cell_size(&self, boundary: &Boundary) -> Vec2 {
+        let size = boundary.size();
+        vec2(size.x / self.columns as f32, size.y / self.rows as f32)
+    }
+
+    fn index(&self, column: isize, row: isize, wrap: bool) -> usize {
+        grid_index(column, row, self.columns as isize, self.rows as isize, wrap)
+    }
+}
+
+/// Grid index of a cell, wrapped round or clamped to the edges.
+fn grid_index(column: isize, row: isize, columns: isize, rows: isize, wrap: bool) -> usize {
+    let (column, row) = if wrap {
+        (column.rem_euclid(columns), row.rem_euclid(rows))
+    } else {
+        (column.clamp(0, columns - 1), row.clamp(0, rows - 1))
+    };
+    (row * columns + column) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chemistry(diffusion: f32, evaporation: f32) -> Chemistry {
+        let mut chemistry = Chemistry::default();
+        chemistry.set_resolution(8, 8);
+        let channel = chemistry.add(Channel {
+            diffusion,
+            evaporation,
+            ..Channel::default()
+        });
+        assert_eq!(channel, 0);
+        chemistry
+    }
+
+    #[test]
+    fn diffusion_spreads_without_losing_any_chemical() {
+        for mode in [BoundaryMode::Walls, BoundaryMode::Wrap] {
+            let boundary = Boundary {
+                mode,
+                ..Boundary::default()
+            };
+            let mut chemistry = chemistry(5.0, 0.0);
+            // One spike in the middle and one in a corner, to reach the edges
+            chemistry.channels[0].values[3 * 8 + 3] = 1.0;
+            chemistry.channels[0].values[0] = 1.0;
+            for _ in 0..20 {
+                chemistry.update(&[], &SpeciesRegistry::default(), &boundary, 0.1);
+            }
+            let values = chemistry.channels[0].values();
+            let total: f32 = values.iter().sum();
+            assert!((total - 2.0).abs() < 1e-4, "{:?}: {}", mode, total);
+            assert!(values[3 * 8 + 3] < 0.5 && values[7 * 8 + 7] > 0.0);
+        }
+    }
+
+    #[test]
+    fn evaporation_fades_every_cell_at_the_same_rate() {
+        let mut chemistry = chemistry(0.0, 1.0);
+        chemistry.channels[0].values.iter_mut().for_each(|value| *value = 2.0);
+        chemistry.update(&[], &SpeciesRegistry::default(), &Boundary::default(), 0.5);
+        let expected = 2.0 * (-0.5f32).exp();
+        assert!(chemistry.channels[0].values().iter().all(|value| (value - expected).abs() < 1e-6));
+    }
+
+    #[test]
+    fn grids_that_do_not_fit_their_channels_are_refused() {
+        let read = |json| serde_json::from_str::<Chemistry>(json);
+        let saved = serde_json::to_string(&chemistry(1.0, 0.0)).unwrap();
+        assert_eq!(read(&saved).unwrap().channels[0].values().len(), 64);
+        assert_eq!(read("{}").unwrap().columns(), 64);
+
+        assert!(read(r#"{ "columns": 0, "rows": 4 }"#).is_err());
+        assert!(read(r#"{ "columns": 2, "rows": 2, "channels": [{ "values": [1, 2, 3] }] }"#).is_err());
+    }
+}
@@ -0,0 +1,112 @@
+use crate::boundary::Boundary;
+use glm::{length, vec2, Vec2};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Most cells one emitter adds in a single step. Anything beyond is dropped
+/// rather than owed, so a long step after a hitch doesn't flood the world.
+pub const MAX_EMITTED_PER_STEP: usize = 64;
+
+/// Spawns cells of one species at a steady rate.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Emitter {
+    pub cell_type: i32,
+    #[serde(with = "crate::scene::vec2_serde")]
+    pub position: Vec2,
+    /// Cells per second.
+    pub rate: f32,
+    /// New cells appear anywhere within this distance of `position`.
+    pub spread: f32,
+    /// Mean direction new cells head off in, in radians.
+    pub direction: f32,
+    /// Width of the cone of directions around `direction`, in radians.
+    /// A full turn sends cells off every which way.
+    pub angle_spread: f32,
+    pub min_speed: f32,
+    pub max_speed: f32,
+    /// Pause while this many cells of the species are within `spread`, so
+    /// a patch of food grows back instead of piling up. 0 never pauses.
+    pub limit: usize,
+    /// Fraction of a cell owed from earlier steps.
+    owed: f32,
+}
+
+impl Default for Emitter {
+    fn default() -> Self {
+        Self::new(0, vec2(0.0, 0.0))
+    }
+}
+
+impl Emitter {
+    pub fn new(cell_type: i32, position: Vec2) -> Self {
+        Self {
+            cell_type,
+            position,
+            rate: 1.0,
+            spread: 0.05,
+            direction: 0.0,
+            angle_spread: std::f32::consts::PI * 2.0,
+            min_speed: 0.0,
+            max_speed: 0.0001,
+            limit: 0,
+            owed: 0.0,
+        }
+    }
+
+    /// Number of cells due after another `dt` seconds, at most
+    /// `MAX_EMITTED_PER_STEP`.
+    pub fn due(&mut self, dt: f32) -> usize {
+        self.owed += self.rate.max(0.0) * dt;
+        let due = self.owed.floor();
+        self.owed -= due;
+        if due >= MAX_EMITTED_PER_STEP as f32 {
+            MAX_EMITTED_PER_STEP
+        } else {
+            due as usize
+        }
+    }
+
+    /// Forget cells owed while paused, so the emitter does not burst when
+    /// it resumes.
+    pub fn pause(&mut self) {
+        self.owed = 0.0;
+    }
+
+    /// Position and velocity for a new cell, uniformly spread over the disc
+    /// and the cone of directions.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> (Vec2, Vec2) {
+        let angle = rng.gen_range(0.0..std::f32::consts::PI * 2.0);
+        let distance = self.spread.max(0.0) * rng.gen::<f32>().sqrt();
+        let position = self.position + vec2(angle.cos(), angle.sin()) * distance;
+
+        let heading = self.direction + self.angle_spread * (rng.gen::<f32>() - 0.5);
+        let (low, high) = (self.min_speed.min(self.max_speed), self.min_speed.max(self.max_speed));
+        let speed = low + (high - low) * rng.gen::<f32>();
+        (position, vec2(heading.cos(), heading.sin()) * speed)
+    }
+}
+
+/// Removes every cell whose centre comes within `radius` of `position`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sink {
+    #[serde(with = "crate::scene::vec2_serde")]
+    pub position: Vec2,
+    pub radius: f32,
+}
+
+impl Default for Sink {
+    fn default() -> Self {
+        Self {
+            position: vec2(0.0, 0.0),
+            radius: 0.05,
+        }
+    }
+}
+
+impl Sink {
+    pub fn contains(&self, point: Vec2, boundary: &Boundary) -> bool {
+        length(boundary.delta(self.position, point)) < self.radius
+    }
+}
@@ -0,0 +1,149 @@
+use crate::objects::Circle;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Heritable traits that have no other home on `Circle`.
+///
+/// Together with the circle's `speed_limit`, `radius`, `friction` and
+/// `brain` these make up everything an offspring inherits from its parent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Genome {
+    /// Neighbours further away than this are not sensed, even if they are
+    /// within the force curve's cutoff.
+    pub sensing_radius: f32,
+    /// Personal adjustment added to the interaction matrix row of the cell's
+    /// type, indexed by the other cell's type. Missing entries count as zero.
+    pub attraction: Vec<f32>,
+}
+
+impl Default for Genome {
+    fn default() -> Self {
+        Self {
+            sensing_radius: 0.5,
+            attraction: Vec::new(),
+        }
+    }
+}
+
+impl Genome {
+    pub fn attraction(&self, cell_type: i32) -> f32 {
+        if cell_type < 0 {
+            return 0.0;
+        }
+        self.attraction.get(cell_type as usize).copied().unwrap_or(0.0)
+    }
+}
+
+/// When and how cells split.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Reproduction {
+    pub enabled: bool,
+    /// A living cell with at least this much energy splits in two, each half
+    /// keeping half of the energy.
+    pub energy_threshold: f32,
+    /// No births happen and emitters pause while the world holds this many
+    /// cells.
+    pub max_population: usize,
+    /// Chance that any one trait mutates in an offspring.
+    pub mutation_rate: f32,
+    /// Largest relative change of a mutated trait. Attraction and brain
+    /// weights change by up to this amount in absolute terms.
+    pub mutation_scale: f32,
+}
+
+impl Default for Reproduction {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            energy_threshold: 150.0,
+            max_population: 5000,
+            mutation_rate: 0.1,
+            mutation_scale: 0.1,
+        }
+    }
+}
+
+impl Reproduction {
+    /// Mutate the heritable traits of a freshly split `child` in place.
+    /// `types` is the number of cell types attraction weights are kept for.
+    pub fn mutate<R: Rng>(&self, child: &mut Circle, types: usize, rng: &mut R) {
+        let scale = self.mutation_scale;
+        // Cells that cannot move, like plants, never evolve into ones that can
+        if child.speed_limit > 0.0 {
+            child.speed_limit = self.mutate_relative(child.speed_limit, rng).max(f32::EPSILON);
+        }
+        child.radius = self.mutate_relative(child.radius, rng).max(0.001);
+        child.friction = self.mutate_relative(child.friction, rng).max(0.0);
+
+        let genome = &mut child.genome;
+        genome.sensing_radius = self.mutate_relative(genome.sensing_radius, rng).max(0.0);
+        if genome.attraction.len() < types {
+            genome.attraction.resize(types, 0.0);
+        }
+        for weight in &mut genome.attraction {
+            if rng.gen::<f32>() < self.mutation_rate {
+                *weight = (*weight + rng.gen_range(-1.0..=1.0) * scale).clamp(-1.0, 1.0);
+            }
+        }
+        if let Some(brain) = &mut child.brain {
+            brain.mutate(self.mutation_rate, scale, rng);
+        }
+    }
+
+    fn mutate_relative<R: Rng>(&self, value: f32, rng: &mut R) -> f32 {
+        if rng.gen::<f32>() < self.mutation_rate {
+            value * (1.0 + rng.gen_range(-1.0..=1.0) * self.mutation_scale)
+        } else {
+            value
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glm::{vec2, vec3};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn mutations_stay_within_scale() {
+        let reproduction = Reproduction {
+            mutation_rate: 1.0,
+            ..Reproduction::default()
+        };
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut parent = Circle::new(0, vec2(0.0, 0.0), 0.01, vec3(1.0, 1.0, 1.0), 0.5);
+        parent.genome.attraction = vec![0.95, 0.0];
+        for _ in 0..100 {
+            let mut child = parent.clone();
+            reproduction.mutate(&mut child, 3, &mut rng);
+            assert!((child.speed_limit - 0.5).abs() <= 0.05 + 1e-6);
+            assert!((child.radius - 0.01).abs() <= 0.001 + 1e-6);
+            assert!((child.genome.sensing_radius - 0.5).abs() <= 0.05 + 1e-6);
+            assert_eq!(child.genome.attraction.len(), 3);
+            for (weight, start) in child.genome.attraction.iter().zip([0.95, 0.0, 0.0]) {
+                assert!((weight - start).abs() <= 0.1 + 1e-6);
+                assert!(*weight <= 1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn stationary_cells_stay_stationary() {
+        let reproduction = Reproduction {
+            mutation_rate: 1.0,
+            mutation_scale: 1.0,
+            ..Reproduction::default()
+        };
+        let mut rng = StdRng::seed_from_u64(5);
+        let plant = Circle::new(0, vec2(0.0, 0.0), 0.01, vec3(0.0, 1.0, 0.0), 0.0);
+        for _ in 0..100 {
+            let mut child = plant.clone();
+            reproduction.mutate(&mut child, 1, &mut rng);
+            assert_eq!(child.speed_limit, 0.0);
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use crate::objects::Circle;
+use glm::Vec2;
+use serde::{Deserialize, Serialize};
+
+/// Numerical scheme used to advance positions and velocities over one step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Integrator {
+    /// Update velocity first, then move with the new velocity. One force evaluation.
+    #[default]
+    SemiImplicitEuler,
+    /// Second order, two force evaluations per step.
+    VelocityVerlet,
+    /// Classic fourth-order Runge-Kutta, four force evaluations per step.
+    Rk4,
+}
+
+/// Fixed-timestep settings used by `World::advance`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimeStep {
+    /// Simulated seconds per fixed step.
+    pub dt: f32,
+    /// Number of equal substeps each fixed step is split into.
+    pub substeps: u32,
+    /// Most fixed steps run for a single call to `advance`. Time beyond that
+    /// is dropped so a slow frame cannot snowball into an ever slower one.
+    pub max_catch_up: u32,
+}
+
+impl Default for TimeStep {
+    fn default() -> Self {
+        Self {
+            dt: 1.0 / 60.0,
+            substeps: 1,
+            max_catch_up: 5,
+        }
+    }
+}
+
+/// Advance `objects` by `dt` with the chosen scheme.
+///
+/// `acceleration` is called with the circles set to each intermediate state
+/// the scheme needs and must return one acceleration per circle.
+pub fn integrate<F>(integrator: Integrator, objects: &mut [Circle], dt: f32, mut acceleration: F)
+where
+    F: FnMut(&[Circle]) -> Vec<Vec2>,
+{
+    match integrator {
+        Integrator::SemiImplicitEuler => {
+            let a = acceleration(objects);
+            for (object, a) in objects.iter_mut().zip(a) {
+                object.velocity = object.velocity + a * dt;
+                object.position = object.position + object.velocity * dt;
+            }
+        }
+        Integrator::VelocityVerlet => {
+            let a0 = acceleration(objects);
+            for (object, a) in objects.iter_mut().zip(&a0) {
+                object.position = object.position + object.velocity * dt + *a * (0.5 * dt * dt);
+                object.velocity = object.velocity + *a * (0.5 * dt);
+            }
+            let a1 = acceleration(objects);
+            for (object, a) in objects.iter_mut().zip(a1) {
+                object.velocity = object.velocity + a * (0.5 * dt);
+            }
+        }
+        Integrator::Rk4 => {
+            let start: Vec<(Vec2, Vec2)> = objects
+                .iter()
+                .map(|object| (object.position, object.velocity))
+                .collect();
+
+            // Each k is (dx/dt, dv/dt) = (velocity, acceleration) at a trial state.
+            let mut slopes: Vec<[(Vec2, Vec2); 4]> = Vec::with_capacity(objects.len());
+            let k1 = acceleration(objects);
+            for (object, a) in objects.iter().zip(k1) {
+                slopes.push([(object.velocity, a); 4]);
+            }
+
+            for (stage, fraction) in [(1, 0.5), (2, 0.5), (3, 1.0)] {
+                for ((object, (x0, v0)), k) in objects.iter_mut().zip(&start).zip(&slopes) {
+                    let (dx, dv) = k[stage - 1];
+                    object.position = *x0 + dx * (dt * fraction);
+                    object.velocity = *v0 + dv * (dt * fraction);
+                }
+                let a = acceleration(objects);
+                for ((object, a), k) in objects.iter().zip(a).zip(slopes.iter_mut()) {
+                    k[stage] = (object.velocity, a);
+                }
+            }
+
+            for ((object, (x0, v0)), k) in objects.iter_mut().zip(&start).zip(&slopes) {
+                let dx = k[0].0 + k[1].0 * 2.0 + k[2].0 * 2.0 + k[3].0;
+                let dv = k[0].1 + k[1].1 * 2.0 + k[2].1 * 2.0 + k[3].1;
+                object.position = *x0 + dx * (dt / 6.0);
+                object.velocity = *v0 + dv * (dt / 6.0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glm::{vec2, vec3};
+
+    /// Run one frictionless cell from `position` for `steps` steps of `dt`.
+    fn run<F>(integrator: Integrator, position: Vec2, dt: f32, steps: usize, force: F) -> Circle
+    where
+        F: Fn(Vec2) -> Vec2,
+    {
+        let mut objects = vec![Circle::new(0, position, 0.01, vec3(1.0, 1.0, 1.0), 1.0)];
+        for _ in 0..steps {
+            integrate(integrator, &mut objects, dt, |objects| {
+                objects.iter().map(|object| force(object.position)).collect()
+            });
+        }
+        objects.remove(0)
+    }
+
+    #[test]
+    fn constant_force_matches_the_analytic_solution() {
+        // x = a t² / 2 after two seconds
+        let gravity = |_| vec2(0.0, -1.0);
+        for integrator in [Integrator::VelocityVerlet, Integrator::Rk4] {
+            let cell = run(integrator, vec2(0.0, 0.0), 0.1, 20, gravity);
+            assert!((cell.position.y + 2.0).abs() < 1e-5, "{:?}: {}", integrator, cell.position.y);
+            assert!((cell.velocity.y + 2.0).abs() < 1e-5, "{:?}: {}", integrator, cell.velocity.y);
+        }
+    }
+
+    #[test]
+    fn spring_follows_the_analytic_solution() {
+        // a = -x starting at rest from x = 1 gives x = cos t
+        let spring = |position: Vec2| -position;
+        let verlet = run(Integrator::VelocityVerlet, vec2(1.0, 0.0), 0.01, 100, spring);
+        let rk4 = run(Integrator::Rk4, vec2(1.0, 0.0), 0.01, 100, spring);
+        let (x, v) = (1.0f32.cos(), -(1.0f32.sin()));
+
+        assert!((verlet.position.x - x).abs() < 1e-4, "{}", verlet.position.x);
+        assert!((verlet.velocity.x - v).abs() < 1e-4, "{}", verlet.velocity.x);
+        assert!((rk4.position.x - x).abs() < 1e-5, "{}", rk4.position.x);
+        assert!((rk4.velocity.x - v).abs() < 1e-5, "{}", rk4.velocity.x);
+    }
+}
@@ -0,0 +1,237 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+/// Signed attraction coefficients between cell types.
+///
+/// `get(a, b)` is how strongly a cell of type `a` is pulled towards a cell of
+/// type `b`: positive values attract, negative values repel and zero ignores.
+/// The matrix does not have to be symmetric.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(try_from = "MatrixFile")]
+pub struct InteractionMatrix {
+    size: usize,
+    values: Vec<f32>,
+}
+
+/// `InteractionMatrix` as read from a file, before its shape is checked.
+#[derive(Deserialize)]
+struct MatrixFile {
+    size: usize,
+    values: Vec<f32>,
+}
+
+impl TryFrom<MatrixFile> for InteractionMatrix {
+    type Error = String;
+
+    fn try_from(file: MatrixFile) -> Result<Self, Self::Error> {
+        if file.size.checked_mul(file.size) != Some(file.values.len()) {
+            return Err(format!(
+                "interaction matrix of size {} has {} values",
+                file.size,
+                file.values.len()
+            ));
+        }
+        Ok(Self {
+            size: file.size,
+            values: file.values,
+        })
+    }
+}
+
+impl InteractionMatrix {
+    pub fn new(size: usize) -> Self {
+        Self {
+            size,
+            values: vec![0.0; size * size],
+        }
+    }
+
+    /// Number of cell types covered by the matrix.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Attraction of `from` towards `to`. Types outside the matrix are ignored.
+    pub fn get(&self, from: i32, to: i32) -> f32 {
+        match (self.index(from), self.index(to)) {
+            (Some(from), Some(to)) => self.values[from * self.size + to],
+            _ => 0.0,
+        }
+    }
+
+    /// Set the attraction of `from` towards `to`, growing the matrix if needed.
+    pub fn set(&mut self, from: i32, to: i32, value: f32) {
+        if from < 0 || to < 0 {
+            return;
+        }
+        let needed = from.max(to) as usize + 1;
+        if needed > self.size {
+            self.resize(needed);
+        }
+        self.values[from as usize * self.size + to as usize] = value;
+    }
+
+    /// Change the number of cell types, keeping the overlapping coefficients.
+    pub fn resize(&mut self, size: usize) {
+        let mut values = vec![0.0; size * size];
+        let keep = self.size.min(size);
+        for from in 0..keep {
+            for to in 0..keep {
+                values[from * size + to] = self.values[from * self.size + to];
+            }
+        }
+        self.size = size;
+        self.values = values;
+    }
+
+    /// Replace every coefficient with a uniformly random one in [-1, 1].
+    pub fn randomize<R: Rng>(&mut self, rng: &mut R) {
+        for value in &mut self.values {
+            *value = rng.gen_range(-1.0..=1.0);
+        }
+    }
+
+    /// Make `get(a, b) == get(b, a)` by averaging each pair.
+    pub fn symmetrize(&mut self) {
+        for from in 0..self.size {
+            for to in from + 1..self.size {
+                let average = (self.values[from * self.size + to] + self.values[to * self.size + from]) * 0.5;
+                self.values[from * self.size + to] = average;
+                self.values[to * self.size + from] = average;
+            }
+        }
+    }
+
+    pub fn zero(&mut self) {
+        self.values.iter_mut().for_each(|value| *value = 0.0);
+    }
+
+    /// Flip attraction into repulsion and back.
+    pub fn invert(&mut self) {
+        self.values.iter_mut().for_each(|value| *value = -*value);
+    }
+
+    /// Drop the row and column of `cell_type`, moving every later type down one.
+    pub fn remove(&mut self, cell_type: i32) {
+        let removed = match self.index(cell_type) {
+            Some(removed) => removed,
+            None => return,
+        };
+        let size = self.size - 1;
+        let mut values = Vec::with_capacity(size * size);
+        for from in (0..self.size).filter(|&from| from != removed) {
+            for to in (0..self.size).filter(|&to| to != removed) {
+                values.push(self.values[from * self.size + to]);
+            }
+        }
+        self.size = size;
+        self.values = values;
+    }
+
+    fn index(&self, cell_type: i32) -> Option<usize> {
+        if cell_type >= 0 && (cell_type as usize) < self.size {
+            Some(cell_type as usize)
+        } else {
+            None
+        }
+    }
+}
+
+/// Shape of the particle-life force as a function of distance.
+///
+/// Closer than `repulsion_radius` every pair pushes apart regardless of type.
+/// Between `repulsion_radius` and `cutoff_radius` the force is a triangle that
+/// peaks half-way at `peak` times the pair's attraction coefficient.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ForceCurve {
+    pub repulsion_radius: f32,
+    pub peak: f32,
+    pub cutoff_radius: f32,
+}
+
+impl Default for ForceCurve {
+    fn default() -> Self {
+        Self {
+            repulsion_radius: 0.15,
+            peak: 1.0,
+            cutoff_radius: 0.5,
+        }
+    }
+}
+
+impl ForceCurve {
+    /// Signed force magnitude along the direction towards the other cell.
+    pub fn force(&self, distance: f32, attraction: f32) -> f32 {
+        if distance >= self.cutoff_radius {
+            0.0
+        } else if distance < self.repulsion_radius {
+            (distance / self.repulsion_radius - 1.0) * self.peak
+        } else {
+            let width = self.cutoff_radius - self.repulsion_radius;
+            let middle = 2.0 * distance - self.repulsion_radius - self.cutoff_radius;
+            attraction * self.peak * (1.0 - middle.abs() / width)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn matrix_is_asymmetric_and_keeps_values_on_resize() {
+        let mut matrix = InteractionMatrix::new(2);
+        matrix.set(0, 1, 0.5);
+        matrix.set(1, 0, -0.25);
+        matrix.set(3, 3, 1.0);
+        assert_eq!(matrix.size(), 4);
+        assert_eq!(matrix.get(0, 1), 0.5);
+        assert_eq!(matrix.get(1, 0), -0.25);
+
+        matrix.resize(2);
+        assert_eq!(matrix.get(0, 1), 0.5);
+        assert_eq!(matrix.get(3, 3), 0.0);
+        assert_eq!(matrix.get(-1, 0), 0.0);
+    }
+
+    #[test]
+    fn editor_operations_reshape_every_coefficient() {
+        let mut matrix = InteractionMatrix::new(3);
+        matrix.randomize(&mut StdRng::seed_from_u64(1));
+        assert!((0..3).all(|from| (0..3).all(|to| matrix.get(from, to).abs() <= 1.0)));
+
+        matrix.set(0, 1, 0.5);
+        matrix.set(1, 0, -0.25);
+        matrix.symmetrize();
+        assert_eq!(matrix.get(0, 1), 0.125);
+        assert_eq!(matrix.get(1, 0), 0.125);
+
+        matrix.invert();
+        assert_eq!(matrix.get(0, 1), -0.125);
+        matrix.zero();
+        assert_eq!(matrix.get(2, 2), 0.0);
+    }
+
+    #[test]
+    fn matrices_of_the_wrong_shape_are_refused() {
+        let read = |json| serde_json::from_str::<InteractionMatrix>(json);
+        assert_eq!(read(r#"{ "size": 2, "values": [1, 2, 3, 4] }"#).unwrap().get(1, 0), 3.0);
+        assert!(read(r#"{ "size": 3, "values": [1, 2] }"#).is_err());
+    }
+
+    #[test]
+    fn force_curve_repels_close_and_peaks_half_way() {
+        let curve = ForceCurve::default();
+        assert_eq!(curve.force(0.0, 1.0), -curve.peak);
+        assert!(curve.force(0.1, 1.0) < 0.0);
+        assert!(curve.force(0.1, -1.0) < 0.0);
+
+        let middle = (curve.repulsion_radius + curve.cutoff_radius) / 2.0;
+        assert!((curve.force(middle, 0.5) - 0.5 * curve.peak).abs() < 1e-6);
+        assert!((curve.force(middle, -0.5) + 0.5 * curve.peak).abs() < 1e-6);
+        assert_eq!(curve.force(curve.cutoff_radius, 1.0), 0.0);
+    }
+}
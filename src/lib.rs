@@ -3,14 +3,40 @@
 
 // Re-export dependencies.
 pub use egui;
+#[cfg(feature = "gui")]
 pub use gl;
+#[cfg(feature = "gui")]
 pub use sdl2;
+pub mod behavior;
+pub mod bonds;
+pub mod boundary;
+pub mod brain;
+pub mod ccd;
+pub mod chemistry;
+pub mod emitters;
+pub mod genetics;
+pub mod integrator;
+pub mod interaction;
+pub mod lifecycle;
+pub mod material;
+pub mod metabolism;
+pub mod objects;
+pub mod obstacles;
+#[cfg(feature = "gui")]
 pub mod painter;
+pub mod scene;
+pub mod spatial;
+pub mod species;
+pub mod stats;
+pub mod timeline;
+pub mod world;
 #[cfg(feature = "use_epi")]
 pub use epi;
+#[cfg(feature = "gui")]
 use painter::Painter;
 #[cfg(feature = "use_epi")]
 use std::time::Instant;
+#[cfg(feature = "gui")]
 use {
     egui::*,
     sdl2::{
@@ -40,11 +66,13 @@ impl RepaintSignal for Signal {
     fn request_repaint(&self) {}
 }
 
+#[cfg(feature = "gui")]
 pub struct FusedCursor {
     pub cursor: Cursor,
     pub icon: SystemCursor,
 }
 
+#[cfg(feature = "gui")]
 impl FusedCursor {
     pub fn new() -> Self {
         Self {
@@ -54,12 +82,14 @@ impl FusedCursor {
     }
 }
 
+#[cfg(feature = "gui")]
 impl Default for FusedCursor {
     fn default() -> Self {
         Self::new()
     }
 }
 
+#[cfg(feature = "gui")]
 pub enum DpiScaling {
     /// Default is handled by sdl2, probably 1.0
     Default,
@@ -67,6 +97,7 @@ pub enum DpiScaling {
     Custom(f32),
 }
 
+#[cfg(feature = "gui")]
 #[derive(Clone)]
 pub enum ShaderVersion {
     /// Default is GLSL 150+.
@@ -75,6 +106,7 @@ pub enum ShaderVersion {
     Adaptive,
 }
 
+#[cfg(feature = "gui")]
 pub struct EguiStateHandler {
     pub fused_cursor: FusedCursor,
     pub pointer_pos: Pos2,
@@ -83,6 +115,7 @@ pub struct EguiStateHandler {
     pub native_pixels_per_point: f32,
 }
 
+#[cfg(feature = "gui")]
 pub fn with_sdl2(
     window: &sdl2::video::Window,
     shader_ver: ShaderVersion,
@@ -106,6 +139,7 @@ pub fn with_sdl2(
     (painter, state_handler)
 }
 
+#[cfg(feature = "gui")]
 impl EguiStateHandler {
     pub fn new(painter: &Painter) -> Self {
         let mut input = egui::RawInput {
@@ -157,6 +191,7 @@ impl EguiStateHandler {
     }
 }
 
+#[cfg(feature = "gui")]
 pub fn input_to_egui(
     window: &sdl2::video::Window,
     event: sdl2::event::Event,
@@ -338,6 +373,7 @@ pub fn input_to_egui(
     }
 }
 
+#[cfg(feature = "gui")]
 pub fn translate_virtual_key_code(key: Keycode) -> Option<egui::Key> {
     Some(match key {
         Keycode::Left => Key::ArrowLeft,
@@ -402,6 +438,7 @@ pub fn translate_virtual_key_code(key: Keycode) -> Option<egui::Key> {
     })
 }
 
+#[cfg(feature = "gui")]
 pub fn translate_cursor(fused: &mut FusedCursor, cursor_icon: egui::CursorIcon) {
     let tmp_icon = match cursor_icon {
         CursorIcon::Crosshair => SystemCursor::Crosshair,
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+/// Stable handle to a cell.
+///
+/// Ids are handed out by `World::spawn` and never reused within a world, so a
+/// stored id either finds the same cell or nothing, however many cells have
+/// been removed in between. `EntityId(0)` is never handed out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize)]
+pub struct EntityId(pub u64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum LifeStage {
+    #[default]
+    Alive,
+    /// Left behind after starving. Still collides and can be eaten, but no
+    /// longer moves on its own, and rots away once `remaining` runs out.
+    Corpse { remaining: f32 },
+    /// Eaten or rotted away. Removed from the world at the end of the step.
+    Dead,
+}
+
+/// What happens to cells once they die.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Lifecycle {
+    /// Starved cells become corpses instead of disappearing straight away.
+    pub leave_corpses: bool,
+    /// Seconds a corpse lasts before it is removed.
+    pub corpse_lifetime: f32,
+}
+
+impl Default for Lifecycle {
+    fn default() -> Self {
+        Self {
+            leave_corpses: true,
+            corpse_lifetime: 10.0,
+        }
+    }
+}
@@ -18,6 +18,8 @@ use sdl2::{event::WindowEvent, keyboard::Keycode, mouse::MouseWheelDirection};
 // Alias the backend to something less mouthful
 use egui_sdl2_gl::{
     self as egui_backend,
+    lifecycle::LifeStage,
+    obstacles::Obstacle,
     painter::{compile_shader, link_program},
 };
 use gl::types::*;
@@ -30,9 +32,6 @@ use window_manager::{
     *,
 };
 
-mod objects;
-use objects::*;
-
 mod camera;
 use camera::*;
 use std::time::*;
@@ -40,50 +39,19 @@ use std::time::*;
 
 use async_std::*;
 
-fn create_circle_ssbo(data: &[f32]) -> GLuint {
+/// Upload `data` to a new shader storage buffer bound at `binding`.
+fn create_ssbo<T>(data: &[T], binding: GLuint) -> GLuint {
     let mut ssbo = 0;
     unsafe {
         gl::GenBuffers(1, &mut ssbo);
         gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, ssbo);
         gl::BufferData(
             gl::SHADER_STORAGE_BUFFER,
-            (data.len() * std::mem::size_of::<f32>()) as GLsizeiptr,
+            std::mem::size_of_val(data) as GLsizeiptr,
             data.as_ptr() as *const _,
             gl::DYNAMIC_DRAW,
         );
-        gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, 2, ssbo);
-    }
-    ssbo
-}
-
-fn create_color_ssbo(data: &[f32]) -> GLuint {
-    let mut ssbo = 0;
-    unsafe {
-        gl::GenBuffers(1, &mut ssbo);
-        gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, ssbo);
-        gl::BufferData(
-            gl::SHADER_STORAGE_BUFFER,
-            (data.len() * std::mem::size_of::<f32>()) as GLsizeiptr,
-            data.as_ptr() as *const _,
-            gl::DYNAMIC_DRAW,
-        );
-        gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, 3, ssbo);
-    }
-    ssbo
-}
-
-fn create_radius_ssbo(data: &[f32]) -> GLuint {
-    let mut ssbo = 0;
-    unsafe {
-        gl::GenBuffers(1, &mut ssbo);
-        gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, ssbo);
-        gl::BufferData(
-            gl::SHADER_STORAGE_BUFFER,
-            (data.len() * std::mem::size_of::<f32>()) as GLsizeiptr,
-            data.as_ptr() as *const _,
-            gl::DYNAMIC_DRAW,
-        );
-        gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, 4, ssbo);
+        gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, binding, ssbo);
     }
     ssbo
 }
@@ -188,10 +156,11 @@ fn main() {
     let mut sandbox_window =SandboxWindow::new();
     let mut main_window = MainWindow::new(&mut sandbox_window);
 // Clone objects from sandbox_window
-    let last_frame_time: Instant = Instant::now();
+    let mut last_frame_time: Instant = Instant::now();
     'running: loop {
         let now = Instant::now();
         let delta_time = now.duration_since(last_frame_time).as_secs_f32();
+        last_frame_time = now;
         egui_state.input.time = Some(start_time.elapsed().as_secs_f64());
 
         egui_ctx.begin_frame(egui_state.input.take());
@@ -213,26 +182,111 @@ fn main() {
         let mut circle_colors = Vec::new();
         let mut circle_radii = Vec::new();
     
-        for object in &main_window.sandbox_window.objects {
+        for object in main_window.sandbox_window.world.objects() {
+            // Corpses are drawn dimmed
+            let color = match object.life {
+                LifeStage::Corpse { .. } => object.color * 0.3,
+                _ => object.color,
+            };
             circle_positions.push(object.position.x);
             circle_positions.push(object.position.y);
-            circle_colors.push(object.color.x);
-            circle_colors.push(object.color.y);
-            circle_colors.push(object.color.z);
+            circle_colors.push(color.x);
+            circle_colors.push(color.y);
+            circle_colors.push(color.z);
             circle_colors.push(1.0); // Assuming alpha = 1.0 for all colors
             circle_radii.push(object.radius);
         }
     
-        let position_ssbo = create_circle_ssbo(&circle_positions);
-        let color_ssbo = create_color_ssbo(&circle_colors);
-        let radius_ssbo = create_radius_ssbo(&circle_radii);
+        // Each obstacle is two vec4s: (kind, first vertex, vertex count, 0)
+        // followed by the shape's parameters. Polygon outlines go into a
+        // separate vertex buffer.
+        let mut obstacle_data = Vec::new();
+        let mut obstacle_vertices = Vec::new();
+
+        for obstacle in &main_window.sandbox_window.world.obstacles {
+            let first_vertex = (obstacle_vertices.len() / 2) as f32;
+            let (kind, count, params) = match obstacle {
+                Obstacle::Segment { start, end } => (0.0, 0, [start.x, start.y, end.x, end.y]),
+                Obstacle::Rect { min, max } => (1.0, 0, [min.x, min.y, max.x, max.y]),
+                Obstacle::Disc { center, radius } => (2.0, 0, [center.x, center.y, *radius, 0.0]),
+                Obstacle::Polygon { points } => {
+                    for point in points {
+                        obstacle_vertices.push(point.x);
+                        obstacle_vertices.push(point.y);
+                    }
+                    (3.0, points.len(), [0.0; 4])
+                }
+            };
+            obstacle_data.extend_from_slice(&[kind, first_vertex, count as f32, 0.0]);
+            obstacle_data.extend_from_slice(&params);
+        }
+
+        // Each bond is two vec4s: both ends, then the colour. Ends are taken
+        // the short way round in wrap mode.
+        let mut bond_data = Vec::new();
+        let world = &main_window.sandbox_window.world;
+        for bond in &world.bonds {
+            if let (Some(a), Some(b)) = (world.get(bond.a), world.get(bond.b)) {
+                let end = a.position + world.boundary.delta(a.position, b.position);
+                let color = (a.color + b.color) * 0.5 / 255.0;
+                bond_data.extend_from_slice(&[a.position.x, a.position.y, end.x, end.y]);
+                bond_data.extend_from_slice(&[color.x, color.y, color.z, 1.0]);
+            }
+        }
+
+        // Chemical channels one after another, plus one colour per channel
+        let mut field_values = Vec::new();
+        let mut field_colors = Vec::new();
+        if main_window.sandbox_window.show_chemicals {
+            for channel in world.chemistry.channels() {
+                field_values.extend_from_slice(channel.values());
+                let color = channel.color / 255.0;
+                field_colors.extend_from_slice(&[color.x, color.y, color.z, 1.0]);
+            }
+        }
+        // Emitters and sinks are drawn as rings: centre and radius, then the
+        // colour. Emitters take their species' colour, sinks are dark.
+        let mut marker_data = Vec::new();
+        for emitter in &world.emitters {
+            let color = world
+                .species
+                .get(emitter.cell_type)
+                .map_or(vec3(1.0, 1.0, 1.0), |species| species.color / 255.0);
+            let radius = emitter.spread.max(0.02);
+            marker_data.extend_from_slice(&[emitter.position.x, emitter.position.y, radius, 0.0]);
+            marker_data.extend_from_slice(&[color.x, color.y, color.z, 1.0]);
+        }
+        for sink in &world.sinks {
+            marker_data.extend_from_slice(&[sink.position.x, sink.position.y, sink.radius, 0.0]);
+            marker_data.extend_from_slice(&[0.15, 0.15, 0.2, 1.0]);
+        }
+
+        let field_min = world.boundary.min;
+        let field_max = world.boundary.max;
+        let field_size = (world.chemistry.columns() as GLint, world.chemistry.rows() as GLint);
+
+        let position_ssbo = create_ssbo(&circle_positions, 2);
+        let color_ssbo = create_ssbo(&circle_colors, 3);
+        let radius_ssbo = create_ssbo(&circle_radii, 4);
+        let obstacle_ssbo = create_ssbo(&obstacle_data, 5);
+        let obstacle_vertex_ssbo = create_ssbo(&obstacle_vertices, 6);
+        let bond_ssbo = create_ssbo(&bond_data, 7);
+        let field_ssbo = create_ssbo(&field_values, 8);
+        let field_color_ssbo = create_ssbo(&field_colors, 9);
+        let marker_ssbo = create_ssbo(&marker_data, 10);
+        main_window.sandbox_window.camera.offset = my_camera.offset;
+        main_window.sandbox_window.camera.zoom = my_camera.zoom;
         main_window.desktop_ui(&egui_ctx);
+        // Loading a scene moves the camera
+        if main_window.sandbox_window.camera.offset != my_camera.offset
+            || main_window.sandbox_window.camera.zoom != my_camera.zoom
+        {
+            my_camera.offset = main_window.sandbox_window.camera.offset;
+            my_camera.zoom = main_window.sandbox_window.camera.zoom;
+            my_camera.set_velocity(vec2(0.0, 0.0));
+        }
         // circle.update(delta_time);
-        main_window.sandbox_window.update_objects(
-            delta_time,
-            SCREEN_WIDTH as f32,
-            SCREEN_HEIGHT as f32,
-        );
+        main_window.sandbox_window.update_objects(delta_time);
 
         unsafe {
             gl::UseProgram(compute_shader_program);
@@ -253,6 +307,23 @@ fn main() {
                 my_camera.offset[1],
             );
             gl::Uniform1f(zoom_loc as GLint, my_camera.zoom);
+
+            // Chemical grid placement
+            let field_min_loc = gl::GetUniformLocation(
+                compute_shader_program,
+                CString::new("field_min").unwrap().as_ptr(),
+            );
+            let field_max_loc = gl::GetUniformLocation(
+                compute_shader_program,
+                CString::new("field_max").unwrap().as_ptr(),
+            );
+            let field_size_loc = gl::GetUniformLocation(
+                compute_shader_program,
+                CString::new("field_size").unwrap().as_ptr(),
+            );
+            gl::Uniform2f(field_min_loc, field_min.x, field_min.y);
+            gl::Uniform2f(field_max_loc, field_max.x, field_max.y);
+            gl::Uniform2i(field_size_loc, field_size.0, field_size.1);
             // Set other circle uniforms as before
             let deltatime_loc = gl::GetUniformLocation(
                 compute_shader_program,
@@ -267,6 +338,20 @@ fn main() {
 
             gl::DispatchCompute(SCREEN_WIDTH / 8, SCREEN_HEIGHT / 8, 1);
             gl::MemoryBarrier(gl::SHADER_IMAGE_ACCESS_BARRIER_BIT);
+
+            // The buffers are rebuilt every frame
+            let buffers = [
+                position_ssbo,
+                color_ssbo,
+                radius_ssbo,
+                obstacle_ssbo,
+                obstacle_vertex_ssbo,
+                bond_ssbo,
+                field_ssbo,
+                field_color_ssbo,
+                marker_ssbo,
+            ];
+            gl::DeleteBuffers(buffers.len() as GLsizei, buffers.as_ptr());
         }
 
         my_camera.update(delta_time);
@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+
+/// Surface properties of a species, used when two cells collide.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Material {
+    /// Fraction of the approach speed kept after a contact. 0 is perfectly
+    /// inelastic, 1 perfectly elastic.
+    pub restitution: f32,
+    /// Coulomb friction coefficient between the surfaces.
+    pub friction: f32,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            restitution: 0.5,
+            friction: 0.1,
+        }
+    }
+}
+
+/// How the materials of two touching cells are merged into one value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CombineRule {
+    Min,
+    Max,
+    #[default]
+    Average,
+    Multiply,
+}
+
+impl CombineRule {
+    pub fn combine(&self, a: f32, b: f32) -> f32 {
+        match self {
+            CombineRule::Min => a.min(b),
+            CombineRule::Max => a.max(b),
+            CombineRule::Average => (a + b) * 0.5,
+            CombineRule::Multiply => a * b,
+        }
+    }
+}
+
+/// World-wide collision settings.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Collisions {
+    pub combine: CombineRule,
+    /// Use this restitution for every contact instead of the materials'.
+    pub restitution_override: Option<f32>,
+    /// Use this friction for every contact instead of the materials'.
+    pub friction_override: Option<f32>,
+    /// Sweep every cell along its path and stop it at the first thing it
+    /// hits, so fast cells cannot tunnel through each other, the walls or
+    /// obstacles. Overlaps are still pushed apart afterwards.
+    pub continuous: bool,
+    /// Most contacts resolved by the sweep in one step. Anything after that
+    /// is left to the overlap pass.
+    pub max_impacts: u32,
+}
+
+impl Default for Collisions {
+    fn default() -> Self {
+        Self {
+            combine: CombineRule::default(),
+            restitution_override: None,
+            friction_override: None,
+            continuous: false,
+            max_impacts: 256,
+        }
+    }
+}
+
+impl Collisions {
+    /// Restitution and friction of a contact between materials `a` and `b`.
+    pub fn contact(&self, a: &Material, b: &Material) -> Material {
+        Material {
+            restitution: self
+                .restitution_override
+                .unwrap_or_else(|| self.combine.combine(a.restitution, b.restitution)),
+            friction: self
+                .friction_override
+                .unwrap_or_else(|| self.combine.combine(a.friction, b.friction)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contacts_combine_materials_unless_overridden() {
+        let bouncy = Material {
+            restitution: 0.9,
+            friction: 0.2,
+        };
+        let dull = Material {
+            restitution: 0.1,
+            friction: 0.4,
+        };
+        let mut collisions = Collisions::default();
+        let contact = collisions.contact(&bouncy, &dull);
+        assert!((contact.restitution - 0.5).abs() < 1e-6 && (contact.friction - 0.3).abs() < 1e-6);
+
+        collisions.combine = CombineRule::Min;
+        assert_eq!(collisions.contact(&bouncy, &dull), Material { restitution: 0.1, friction: 0.2 });
+        collisions.combine = CombineRule::Multiply;
+        assert!((collisions.contact(&bouncy, &dull).friction - 0.08).abs() < 1e-6);
+
+        collisions.restitution_override = Some(1.0);
+        collisions.combine = CombineRule::Max;
+        assert_eq!(collisions.contact(&bouncy, &dull), Material { restitution: 1.0, friction: 0.4 });
+    }
+}
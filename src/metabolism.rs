@@ -0,0 +1,68 @@
+use crate::objects::Circle;
+use glm::length;
+use serde::{Deserialize, Serialize};
+
+/// How cells burn and gain energy.
+///
+/// Every second a cell burns `basal_rate + movement_cost * speed` energy per
+/// unit of mass, so big and fast cells starve first. A cell whose energy
+/// reaches zero dies. Eating a cell restores `energy_per_mass` for every unit
+/// of the prey's mass.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Metabolism {
+    pub enabled: bool,
+    pub basal_rate: f32,
+    pub movement_cost: f32,
+    pub energy_per_mass: f32,
+}
+
+impl Default for Metabolism {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            basal_rate: 1.0,
+            movement_cost: 10.0,
+            energy_per_mass: 50.0,
+        }
+    }
+}
+
+impl Metabolism {
+    /// Energy `object` burns over `dt` seconds.
+    pub fn drain(&self, object: &Circle, dt: f32) -> f32 {
+        if !self.enabled {
+            return 0.0;
+        }
+        let speed = length(object.velocity);
+        object.mass * (self.basal_rate + self.movement_cost * speed) * dt
+    }
+
+    /// Energy gained from eating `prey`.
+    pub fn gain(&self, prey: &Circle) -> f32 {
+        prey.mass * self.energy_per_mass
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glm::{vec2, vec3};
+
+    #[test]
+    fn drain_grows_with_mass_and_speed() {
+        let metabolism = Metabolism::default();
+        let mut cell = Circle::new(0, vec2(0.0, 0.0), 0.01, vec3(1.0, 1.0, 1.0), 1.0);
+        assert_eq!(metabolism.drain(&cell, 0.5), 0.5);
+
+        cell.mass = 2.0;
+        cell.velocity = vec2(0.3, 0.4);
+        assert_eq!(metabolism.drain(&cell, 0.5), 2.0 * (1.0 + 10.0 * 0.5) * 0.5);
+
+        let off = Metabolism {
+            enabled: false,
+            ..metabolism
+        };
+        assert_eq!(off.drain(&cell, 0.5), 0.0);
+    }
+}
@@ -1,25 +1,42 @@
+use crate::boundary::Boundary;
+use crate::brain::Brain;
+use crate::genetics::Genome;
+use crate::interaction::{ForceCurve, InteractionMatrix};
+use crate::lifecycle::{EntityId, LifeStage};
+use crate::metabolism::Metabolism;
 use glm::{length, normalize, vec2, Vec2, Vec3};
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Circle {
+    pub id: EntityId,     // assigned by the world on spawn
+    #[serde(with = "crate::scene::vec2_serde")]
     pub position: Vec2,   // center of the circle
+    #[serde(with = "crate::scene::vec2_serde")]
     pub velocity: Vec2,   // velocity of the circle
     pub radius: f32,      // radius of the circle
     pub mass: f32,        // mass of the circle
+    #[serde(with = "crate::scene::vec3_serde")]
     pub color: Vec3,      // color of the circle (RGB)
     pub friction: f32,    // friction coefficient
     pub speed_limit: f32, // maximum speed limit
     pub cell_type: i32,   // type of the circle
-    pub predators: Vec<i32>,
-    pub friends: Vec<i32>,
-    pub foods: Vec<i32>,
-    pub hunger: f32,
+    pub foods: Vec<i32>,  // cell types this circle eats on contact
+    pub energy: f32,      // dies when this reaches zero
+    pub life: LifeStage,
+    pub genome: Genome,
+    pub parent: Option<EntityId>, // cell this one split from, if it was born
+    pub lineage: EntityId,        // id of the founder this cell descends from
+    pub generation: u32,          // splits since the founder
+    #[serde(default)]
+    pub brain: Option<Brain>,     // steers the cell under the neural behaviour
 }
 
 impl Circle {
     pub fn new(cell_type: i32, position: Vec2, radius: f32, color: Vec3, speed_limit: f32) -> Self {
         Circle {
+            id: EntityId::default(),
             position,
             velocity: vec2(0.0, 0.0),
             radius,
@@ -28,10 +45,14 @@ impl Circle {
             friction: 0.5, // default friction
             speed_limit,
             cell_type,
-            predators: [].to_vec(),
-            friends: [].to_vec(),
             foods: [].to_vec(),
-            hunger: 100.0,
+            energy: 100.0,
+            life: LifeStage::Alive,
+            genome: Genome::default(),
+            parent: None,
+            lineage: EntityId::default(),
+            generation: 0,
+            brain: None,
         }
     }
 
@@ -39,58 +60,68 @@ impl Circle {
         std::f32::consts::PI * self.radius * self.radius
     }
 
-    pub fn update(&mut self, dt: f32) {
-        // Apply friction
-        self.velocity = self.velocity * (1.0 - self.friction * dt);
-        // Limit velocity
+    /// Clamp the velocity to the speed limit.
+    pub fn limit_speed(&mut self) {
         let current_speed = length(self.velocity);
         if current_speed > self.speed_limit {
             let direction = normalize(self.velocity);
             self.velocity = direction * self.speed_limit;
         }
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.life == LifeStage::Alive
+    }
 
-        // Update position based on velocity
-        self.position = self.position + self.velocity * dt;
+    /// Living cells eat anything on their food list that has not already been eaten.
+    pub fn can_eat(&self, object: &Circle) -> bool {
+        self.is_alive() && object.life != LifeStage::Dead && self.foods.contains(&object.cell_type)
     }
-    pub fn eat(&mut self, object:&mut Circle){
-        self.hunger+=10.0;
-        object.cell_type=0;
+
+    pub fn eat(&mut self, object: &mut Circle, metabolism: &Metabolism) {
+        self.energy += metabolism.gain(object);
+        object.life = LifeStage::Dead;
     }
-    pub fn decide(&mut self, objects: &mut Vec<Circle>) {
-        let follow_radius = 0.5;
+
+    /// Sum of the particle-life forces `neighbours` exert on this circle, or
+    /// `None` if none of them is within both the force curve's cutoff and the
+    /// circle's sensing radius. `neighbours` must not contain the circle itself.
+    pub fn interaction_force<'a, I: IntoIterator<Item = &'a Circle>>(
+        &self,
+        neighbours: I,
+        interactions: &InteractionMatrix,
+        curve: &ForceCurve,
+        boundary: &Boundary,
+    ) -> Option<Vec2> {
+        let mut force = vec2(0.0, 0.0);
         let mut following = false;
+        let range = curve.cutoff_radius.min(self.genome.sensing_radius);
 
-        for object in objects.iter() {
-            if *object != *self {
-                // Calculate distance between self and object
-                let dx = object.position.x - self.position.x;
-                let dy = object.position.y - self.position.y;
-                let distance = (dx * dx + dy * dy).sqrt();
+        for object in neighbours {
+            let delta = boundary.delta(self.position, object.position);
+            let distance = length(delta);
 
-                if distance <= follow_radius && distance != 0.0 {
-                    if self.predators.contains(&object.cell_type) {
-                        escape_particle(self, object, distance, 1.0, 1.0);
-                    }
-                    if self.friends.contains(&object.cell_type) {
-                        follow_particle(self, object, distance, 1.0, 1.0);
-                    }
-                    if self.foods.contains(&object.cell_type) {
-                        follow_particle(self, object, distance, 1.0, 1.0);
-                    }
-                    // Call follow_particle function
-                    following = true;
-                }
+            if distance < range && distance != 0.0 {
+                let attraction = interactions.get(self.cell_type, object.cell_type)
+                    + self.genome.attraction(object.cell_type);
+                force = force + delta / distance * curve.force(distance, attraction);
+                following = true;
             }
         }
 
-        if !following {
-            // If not following any particle, move randomly
-            self.move_randomly();
+        if following {
+            Some(force)
+        } else {
+            None
         }
     }
 
-    fn move_randomly(&mut self) {
-        let mut rng = rand::thread_rng();
+    /// Head off in a random direction at a random speed below the speed
+    /// limit. Cells that aren't allowed to move stay as they are.
+    pub fn move_randomly<R: Rng>(&mut self, rng: &mut R) {
+        if self.speed_limit <= 0.0 {
+            return;
+        }
         let random_angle: f32 = rng.gen_range(0.0..std::f32::consts::PI * 2.0);
         let random_speed: f32 = rng.gen_range(0.0..self.speed_limit);
 
@@ -98,39 +129,3 @@ impl Circle {
         self.velocity.y = random_speed * random_angle.sin();
     }
 }
-
-fn follow_particle(
-    particle: &mut Circle,
-    target: &Circle,
-    distance: f32,
-    speed: f32,
-    multiplier: f32,
-) {
-    let dx = target.position.x - particle.position.x;
-    let dy = target.position.y - particle.position.y;
-
-    let vx = (dx / distance) * speed * multiplier;
-    let vy = (dy / distance) * speed * multiplier;
-
-    particle.velocity.x += vx;
-    particle.velocity.y += vy;
-}
-fn escape_particle(
-    particle: &mut Circle,
-    target: &Circle,
-    distance: f32,
-    speed: f32,
-    multiplier: f32,
-) {
-    let dx = target.position.x - particle.position.x;
-    let dy = target.position.y - particle.position.y;
-
-    let vx = (dx / distance) * speed * multiplier;
-    let vy = (dy / distance) * speed * multiplier;
-
-    particle.velocity.x -= vx;
-    particle.velocity.y -= vy;
-}
-fn main(){
-    
-}
\ No newline at end of file
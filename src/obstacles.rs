@@ -0,0 +1,253 @@
+use crate::boundary::{Boundary, BoundaryMode};
+use crate::objects::Circle;
+use glm::{dot, length, vec2, Vec2};
+use serde::{Deserialize, Serialize};
+
+/// Static piece of arena geometry that cells collide with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Obstacle {
+    /// A wall of zero thickness.
+    Segment {
+        #[serde(with = "crate::scene::vec2_serde")]
+        start: Vec2,
+        #[serde(with = "crate::scene::vec2_serde")]
+        end: Vec2,
+    },
+    /// Solid axis-aligned box.
+    Rect {
+        #[serde(with = "crate::scene::vec2_serde")]
+        min: Vec2,
+        #[serde(with = "crate::scene::vec2_serde")]
+        max: Vec2,
+    },
+    /// Solid disc.
+    Disc {
+        #[serde(with = "crate::scene::vec2_serde")]
+        center: Vec2,
+        radius: f32,
+    },
+    /// Solid polygon. The points may wind either way but must not cross.
+    Polygon {
+        #[serde(with = "crate::scene::vec2_list_serde")]
+        points: Vec<Vec2>,
+    },
+}
+
+impl Obstacle {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Obstacle::Segment { .. } => "Segment",
+            Obstacle::Rect { .. } => "Box",
+            Obstacle::Disc { .. } => "Disc",
+            Obstacle::Polygon { .. } => "Polygon",
+        }
+    }
+
+    /// Middle of the obstacle's bounding box.
+    pub fn center(&self) -> Vec2 {
+        match self {
+            Obstacle::Segment { start, end } => (*start + *end) * 0.5,
+            Obstacle::Rect { min, max } => (*min + *max) * 0.5,
+            Obstacle::Disc { center, .. } => *center,
+            Obstacle::Polygon { points } => {
+                let first = match points.first() {
+                    Some(&first) => first,
+                    None => return vec2(0.0, 0.0),
+                };
+                let (min, max) = points.iter().fold((first, first), |(min, max), point| {
+                    (
+                        vec2(min.x.min(point.x), min.y.min(point.y)),
+                        vec2(max.x.max(point.x), max.y.max(point.y)),
+                    )
+                });
+                (min + max) * 0.5
+            }
+        }
+    }
+
+    /// Closest point on the outline to `point`, and whether `point` is inside
+    /// the obstacle. `None` for a polygon without any points.
+    pub fn closest_point(&self, point: Vec2) -> Option<(Vec2, bool)> {
+        let closest = match self {
+            Obstacle::Segment { start, end } => (closest_on_segment(point, *start, *end), false),
+            Obstacle::Rect { min, max } => {
+                let inside = point.x > min.x && point.x < max.x && point.y > min.y && point.y < max.y;
+                if !inside {
+                    let clamped = vec2(point.x.clamp(min.x, max.x), point.y.clamp(min.y, max.y));
+                    return Some((clamped, false));
+                }
+                // Push out through the nearest edge
+                let edges = [
+                    (point.x - min.x, vec2(min.x, point.y)),
+                    (max.x - point.x, vec2(max.x, point.y)),
+                    (point.y - min.y, vec2(point.x, min.y)),
+                    (max.y - point.y, vec2(point.x, max.y)),
+                ];
+                let nearest = edges
+                    .iter()
+                    .fold(edges[0], |best, edge| if edge.0 < best.0 { *edge } else { best });
+                (nearest.1, true)
+            }
+            Obstacle::Disc { center, radius } => {
+                let offset = point - *center;
+                let distance = length(offset);
+                if distance == 0.0 {
+                    (*center + vec2(*radius, 0.0), true)
+                } else {
+                    (*center + offset / distance * *radius, distance < *radius)
+                }
+            }
+            Obstacle::Polygon { points } => {
+                if points.is_empty() {
+                    return None;
+                }
+                let mut closest = point;
+                let mut best = f32::INFINITY;
+                let mut inside = false;
+                for (i, &a) in points.iter().enumerate() {
+                    let b = points[(i + 1) % points.len()];
+                    let candidate = closest_on_segment(point, a, b);
+                    let distance = length(point - candidate);
+                    if distance < best {
+                        best = distance;
+                        closest = candidate;
+                    }
+                    // Even-odd rule
+                    if (a.y > point.y) != (b.y > point.y)
+                        && point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x)
+                    {
+                        inside = !inside;
+                    }
+                }
+                (closest, inside)
+            }
+        };
+        Some(closest)
+    }
+
+    /// The copy of `point` nearest the obstacle. That is `point` itself,
+    /// unless the world wraps and a copy across a seam is closer.
+    pub fn nearest_image(&self, point: Vec2, boundary: &Boundary) -> Vec2 {
+        if boundary.mode == BoundaryMode::Wrap {
+            let center = self.center();
+            center + boundary.delta(center, point)
+        } else {
+            point
+        }
+    }
+
+    /// Signed distance from `point` to the outline, negative inside, and the
+    /// normal pointing out of the obstacle towards `point`.
+    pub fn signed_distance(&self, point: Vec2) -> Option<(f32, Vec2)> {
+        let (closest, inside) = self.closest_point(point)?;
+        let offset = point - closest;
+        let distance = length(offset);
+        Some(if distance == 0.0 {
+            (0.0, vec2(0.0, 1.0))
+        } else if inside {
+            (-distance, offset / -distance)
+        } else {
+            (distance, offset / distance)
+        })
+    }
+
+    /// Push `object` out of the obstacle and bounce it off the surface,
+    /// keeping the boundary's restitution of the speed along the contact
+    /// normal. In wrap mode the obstacle is also hit across the seams.
+    pub fn resolve(&self, object: &mut Circle, boundary: &Boundary) {
+        let position = self.nearest_image(object.position, boundary);
+        let (distance, normal) = match self.signed_distance(position) {
+            Some(surface) => surface,
+            None => return,
+        };
+        if distance >= object.radius {
+            return;
+        }
+        object.position = object.position + normal * (object.radius - distance);
+
+        let velocity_along_normal = dot(object.velocity, normal);
+        if velocity_along_normal < 0.0 {
+            object.velocity =
+                object.velocity - normal * ((1.0 + boundary.restitution) * velocity_along_normal);
+        }
+    }
+}
+
+fn closest_on_segment(point: Vec2, start: Vec2, end: Vec2) -> Vec2 {
+    let along = end - start;
+    let length_squared = dot(along, along);
+    if length_squared == 0.0 {
+        return start;
+    }
+    let t = (dot(point - start, along) / length_squared).clamp(0.0, 1.0);
+    start + along * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glm::vec3;
+
+    fn cell(x: f32, y: f32, velocity: Vec2) -> Circle {
+        let mut cell = Circle::new(0, vec2(x, y), 0.1, vec3(1.0, 1.0, 1.0), 1.0);
+        cell.velocity = velocity;
+        cell
+    }
+
+    #[test]
+    fn cells_are_pushed_out_and_bounced() {
+        let boundary = Boundary {
+            restitution: 0.5,
+            ..Boundary::default()
+        };
+        let rect = Obstacle::Rect {
+            min: vec2(-0.2, -0.2),
+            max: vec2(0.2, 0.2),
+        };
+        let mut inside = cell(0.15, 0.0, vec2(-1.0, 0.0));
+        rect.resolve(&mut inside, &boundary);
+        assert!((inside.position.x - 0.3).abs() < 1e-6, "{:?}", inside.position);
+        assert!((inside.velocity.x - 0.5).abs() < 1e-6, "{:?}", inside.velocity);
+
+        let disc = Obstacle::Disc {
+            center: vec2(0.0, 0.0),
+            radius: 0.2,
+        };
+        let mut clear = cell(0.5, 0.0, vec2(-1.0, 0.0));
+        disc.resolve(&mut clear, &boundary);
+        assert_eq!(clear.position, vec2(0.5, 0.0));
+        assert_eq!(clear.velocity, vec2(-1.0, 0.0));
+    }
+
+    #[test]
+    fn polygons_contain_their_inside() {
+        let triangle = Obstacle::Polygon {
+            points: vec![vec2(0.0, 0.0), vec2(1.0, 0.0), vec2(0.0, 1.0)],
+        };
+        assert!(triangle.closest_point(vec2(0.2, 0.2)).unwrap().1);
+        assert!(!triangle.closest_point(vec2(0.8, 0.8)).unwrap().1);
+        assert!(Obstacle::Polygon { points: Vec::new() }.closest_point(vec2(0.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn wrapped_cells_hit_obstacles_across_the_seam() {
+        let mut boundary = Boundary {
+            mode: BoundaryMode::Wrap,
+            ..Boundary::default()
+        };
+        let wall = Obstacle::Segment {
+            start: vec2(0.95, -0.5),
+            end: vec2(0.95, 0.5),
+        };
+        // Just past the right edge, the cell overlaps the wall from the right
+        let mut across = cell(-0.98, 0.0, vec2(-1.0, 0.0));
+        wall.resolve(&mut across, &boundary);
+        assert!((across.position.x + 0.95).abs() < 1e-5, "{:?}", across.position);
+        assert!(across.velocity.x > 0.0);
+
+        boundary.mode = BoundaryMode::Walls;
+        let mut walled = cell(-0.98, 0.0, vec2(-1.0, 0.0));
+        wall.resolve(&mut walled, &boundary);
+        assert_eq!(walled.position, vec2(-0.98, 0.0));
+    }
+}
@@ -0,0 +1,228 @@
+use crate::bonds::Bond;
+use crate::boundary::Boundary;
+use crate::chemistry::Chemistry;
+use crate::emitters::{Emitter, Sink};
+use crate::genetics::Reproduction;
+use crate::integrator::{Integrator, TimeStep};
+use crate::interaction::{ForceCurve, InteractionMatrix};
+use crate::lifecycle::Lifecycle;
+use crate::material::Collisions;
+use crate::metabolism::Metabolism;
+use crate::objects::Circle;
+use crate::obstacles::Obstacle;
+use crate::species::SpeciesRegistry;
+use crate::world::{BroadPhase, EventCounters, World};
+use glm::{vec2, Vec2};
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+/// Version written into every scene file. Bump it whenever the format
+/// changes in a way `Scene::migrate` has to know about. New fields that
+/// fall back to a default when missing don't count: older files still load
+/// as they are, so such additions keep the version where it is.
+pub const SCENE_VERSION: u32 = 1;
+
+/// Where the sandbox camera was looking.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SceneCamera {
+    #[serde(with = "vec2_serde")]
+    pub offset: Vec2,
+    pub zoom: f32,
+}
+
+impl Default for SceneCamera {
+    fn default() -> Self {
+        Self {
+            offset: vec2(0.0, 0.0),
+            zoom: 1.0,
+        }
+    }
+}
+
+/// Everything needed to put a world back exactly as it was, plus the bits of
+/// sandbox state worth keeping. Stored on disk as pretty-printed JSON.
+///
+/// Fields missing from a file take the values of a fresh `World`, so files
+/// written before a field existed keep loading.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scene {
+    pub version: u32,
+    pub seed: u64,
+    /// Exact RNG state. `None` restarts the stream from `seed`.
+    pub rng: Option<ChaCha12Rng>,
+    pub next_id: u64,
+    /// Steps the world had taken.
+    pub steps: u64,
+    pub events: EventCounters,
+    pub species: SpeciesRegistry,
+    pub interactions: InteractionMatrix,
+    pub force_curve: ForceCurve,
+    pub metabolism: Metabolism,
+    pub lifecycle: Lifecycle,
+    pub reproduction: Reproduction,
+    pub boundary: Boundary,
+    pub collisions: Collisions,
+    pub obstacles: Vec<Obstacle>,
+    pub bonds: Vec<Bond>,
+    pub chemistry: Chemistry,
+    pub emitters: Vec<Emitter>,
+    pub sinks: Vec<Sink>,
+    pub broad_phase: BroadPhase,
+    pub integrator: Integrator,
+    pub time_step: TimeStep,
+    pub threads: usize,
+    pub objects: Vec<Circle>,
+    /// Template the sandbox's "Add Circle" button spawns.
+    pub default_object: Option<Circle>,
+    pub camera: SceneCamera,
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        World::new().to_scene()
+    }
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(std::io::Error),
+    Format(serde_json::Error),
+    /// Written by a newer version of the program.
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(error) => write!(f, "{}", error),
+            SceneError::Format(error) => write!(f, "invalid scene file: {}", error),
+            SceneError::UnsupportedVersion(version) => write!(
+                f,
+                "scene version {} is newer than the supported version {}",
+                version, SCENE_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<std::io::Error> for SceneError {
+    fn from(error: std::io::Error) -> Self {
+        SceneError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for SceneError {
+    fn from(error: serde_json::Error) -> Self {
+        SceneError::Format(error)
+    }
+}
+
+impl Scene {
+    pub fn to_json(&self) -> Result<String, SceneError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, SceneError> {
+        let scene: Scene = serde_json::from_str(json)?;
+        scene.migrate()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SceneError> {
+        std::fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SceneError> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    /// Bring a scene read from an older file up to `SCENE_VERSION`.
+    fn migrate(mut self) -> Result<Self, SceneError> {
+        if self.version > SCENE_VERSION {
+            return Err(SceneError::UnsupportedVersion(self.version));
+        }
+        // Version 1 is the first format, nothing to upgrade yet.
+        self.version = SCENE_VERSION;
+        Ok(self)
+    }
+}
+
+/// glm vectors are stored as plain `[x, y]` arrays.
+pub(crate) mod vec2_serde {
+    use glm::{vec2, Vec2};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &Vec2, serializer: S) -> Result<S::Ok, S::Error> {
+        [value.x, value.y].serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec2, D::Error> {
+        let [x, y] = <[f32; 2]>::deserialize(deserializer)?;
+        Ok(vec2(x, y))
+    }
+}
+
+/// `[r, g, b]`, see `vec2_serde`.
+pub(crate) mod vec3_serde {
+    use glm::{vec3, Vec3};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &Vec3, serializer: S) -> Result<S::Ok, S::Error> {
+        [value.x, value.y, value.z].serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec3, D::Error> {
+        let [x, y, z] = <[f32; 3]>::deserialize(deserializer)?;
+        Ok(vec3(x, y, z))
+    }
+}
+
+/// A list of points as `[[x, y], ...]`, see `vec2_serde`.
+pub(crate) mod vec2_list_serde {
+    use glm::{vec2, Vec2};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(values: &[Vec2], serializer: S) -> Result<S::Ok, S::Error> {
+        let points: Vec<[f32; 2]> = values.iter().map(|value| [value.x, value.y]).collect();
+        points.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Vec2>, D::Error> {
+        let points = Vec::<[f32; 2]>::deserialize(deserializer)?;
+        Ok(points.into_iter().map(|[x, y]| vec2(x, y)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_fields_fall_back_to_a_fresh_world() {
+        let scene = Scene::from_json(r#"{ "version": 1, "seed": 7 }"#).unwrap();
+        assert_eq!(scene.seed, 7);
+        assert_eq!(scene.species, SpeciesRegistry::default());
+        assert_eq!(scene.boundary, Boundary::default());
+        assert!(scene.objects.is_empty());
+    }
+
+    #[test]
+    fn newer_versions_are_refused() {
+        let json = format!(r#"{{ "version": {} }}"#, SCENE_VERSION + 1);
+        match Scene::from_json(&json) {
+            Err(SceneError::UnsupportedVersion(version)) => assert_eq!(version, SCENE_VERSION + 1),
+            other => panic!("expected a version error, got {:?}", other.map(|scene| scene.version)),
+        }
+    }
+
+    #[test]
+    fn truncated_interaction_matrices_are_refused() {
+        let json = r#"{ "version": 1, "interactions": { "size": 4, "values": [0.5] } }"#;
+        assert!(matches!(Scene::from_json(json), Err(SceneError::Format(_))));
+    }
+}
@@ -0,0 +1,161 @@
+use ahash::AHashMap;
+use glm::Vec2;
+
+/// Uniform grid used as a broad phase for neighbour queries and collisions.
+///
+/// The grid is rebuilt from scratch every step. Indices are stored sorted by
+/// cell and then by index, and every query returns indices in ascending order,
+/// so callers visit candidates in the same order as a brute-force scan would.
+#[derive(Debug, Clone, Default)]
+pub struct SpatialHash {
+    cell_size: (f32, f32),
+    entries: Vec<((i32, i32), usize)>,
+    cells: AHashMap<(i32, i32), (usize, usize)>,
+    /// Origin and cell counts of a periodic grid, see `rebuild_wrapped`.
+    wrap: Option<(Vec2, (i32, i32))>,
+}
+
+impl SpatialHash {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Re-bucket every position. The position's index in the iterator is the
+    /// index reported back by `query` and `pairs`.
+    pub fn rebuild<I: IntoIterator<Item = Vec2>>(&mut self, cell_size: f32, positions: I) {
+        let cell_size = cell_size.max(f32::EPSILON);
+        self.cell_size = (cell_size, cell_size);
+        self.wrap = None;
+        self.fill(positions);
+    }
+
+    /// Like `rebuild`, but for a periodic box starting at `min` and `size`
+    /// wide, where cells on opposite edges are neighbours. Cells are stretched
+    /// along each axis so a whole number of them tiles the box.
+    pub fn rebuild_wrapped<I: IntoIterator<Item = Vec2>>(
+        &mut self,
+        cell_size: f32,
+        min: Vec2,
+        size: Vec2,
+        positions: I,
+    ) {
+        let cell_size = cell_size.max(f32::EPSILON);
+        let columns = ((size.x / cell_size).floor() as i32).max(1);
+        let rows = ((size.y / cell_size).floor() as i32).max(1);
+        self.cell_size = (size.x / columns as f32, size.y / rows as f32);
+        self.wrap = Some((min, (columns, rows)));
+        self.fill(positions);
+    }
+
+    fn fill<I: IntoIterator<Item = Vec2>>(&mut self, positions: I) {
+        self.entries.clear();
+        self.cells.clear();
+
+        for (index, position) in positions.into_iter().enumerate() {
+            self.entries.push((self.key(position), index));
+        }
+        self.entries.sort_unstable();
+
+        let mut start = 0;
+        while start < self.entries.len() {
+            let key = self.entries[start].0;
+            let mut end = start + 1;
+            while end < self.entries.len() && self.entries[end].0 == key {
+                end += 1;
+            }
+            self.cells.insert(key, (start, end));
+            start = end;
+        }
+    }
+
+    /// Push every index whose cell overlaps the square around `center` into
+    /// `out`, in ascending order. Callers still have to check exact distances.
+    pub fn query(&self, center: Vec2, radius: f32, out: &mut Vec<usize>) {
+        let first = out.len();
+        let (min_x, min_y) = self.raw_key(glm::vec2(center.x - radius, center.y - radius));
+        let (mut max_x, mut max_y) = self.raw_key(glm::vec2(center.x + radius, center.y + radius));
+        // A wide query on a small periodic grid would visit some cells twice
+        if let Some((_, (columns, rows))) = self.wrap {
+            max_x = max_x.min(min_x.saturating_add(columns - 1));
+            max_y = max_y.min(min_y.saturating_add(rows - 1));
+        }
+
+        for x in min_x..=max_x {
+            for y in min_y..=max_y {
+                out.extend(self.cell(self.wrap_key((x, y))));
+            }
+        }
+        out[first..].sort_unstable();
+    }
+
+    /// Every pair `(i, j)` with `i < j` whose cells touch, sorted the same way
+    /// a nested brute-force loop would visit them. With a cell size of at least
+    /// the largest diameter this contains every overlapping pair.
+    pub fn pairs(&self) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        for &((x, y), i) in &self.entries {
+            for dx in -1..=1 {
+                for dy in -1..=1 {
+                    let neighbour = self.wrap_key((x.saturating_add(dx), y.saturating_add(dy)));
+                    pairs.extend(self.cell(neighbour).filter(|&j| j > i).map(|j| (i, j)));
+                }
+            }
+        }
+        pairs.sort_unstable();
+        pairs.dedup();
+        pairs
+    }
+
+    fn cell(&self, key: (i32, i32)) -> impl Iterator<Item = usize> + '_ {
+        let (start, end) = self.cells.get(&key).copied().unwrap_or((0, 0));
+        self.entries[start..end].iter().map(|&(_, index)| index)
+    }
+
+    fn key(&self, position: Vec2) -> (i32, i32) {
+        self.wrap_key(self.raw_key(position))
+    }
+
+    fn raw_key(&self, position: Vec2) -> (i32, i32) {
+        let origin = self.wrap.map_or(glm::vec2(0.0, 0.0), |(min, _)| min);
+        (
+            ((position.x - origin.x) / self.cell_size.0).floor() as i32,
+            ((position.y - origin.y) / self.cell_size.1).floor() as i32,
+        )
+    }
+
+    fn wrap_key(&self, (x, y): (i32, i32)) -> (i32, i32) {
+        match self.wrap {
+            Some((_, (columns, rows))) => (x.rem_euclid(columns), y.rem_euclid(rows)),
+            None => (x, y),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glm::vec2;
+
+    #[test]
+    fn queries_and_pairs_come_back_in_index_order() {
+        let positions = [vec2(0.9, 0.9), vec2(0.05, 0.05), vec2(0.15, 0.05), vec2(0.0, 0.12)];
+        let mut grid = SpatialHash::new();
+        grid.rebuild(0.1, positions.iter().copied());
+
+        let mut found = Vec::new();
+        grid.query(vec2(0.05, 0.05), 0.1, &mut found);
+        assert_eq!(found, vec![1, 2, 3]);
+        assert_eq!(grid.pairs(), vec![(1, 2), (1, 3), (2, 3)]);
+    }
+
+    #[test]
+    fn wide_wrapped_queries_report_each_index_once() {
+        let positions = [vec2(0.1, 0.1), vec2(0.6, 0.6)];
+        let mut grid = SpatialHash::new();
+        grid.rebuild_wrapped(0.5, vec2(0.0, 0.0), vec2(1.0, 1.0), positions.iter().copied());
+
+        let mut found = Vec::new();
+        grid.query(vec2(0.5, 0.5), 2.0, &mut found);
+        assert_eq!(found, vec![0, 1]);
+    }
+}
@@ -0,0 +1,279 @@
+use crate::behavior::BehaviorKind;
+use crate::bonds::Bonding;
+use crate::chemistry::ChemicalResponse;
+use crate::interaction::InteractionMatrix;
+use crate::material::Material;
+use crate::objects::Circle;
+use glm::{vec3, Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+
+/// How one species treats another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Relationship {
+    #[default]
+    Neutral,
+    /// Drawn towards it.
+    Friend,
+    /// Hunted and eaten on contact.
+    Food,
+    /// Fled from.
+    Predator,
+}
+
+impl Relationship {
+    pub const ALL: [Relationship; 4] = [
+        Relationship::Neutral,
+        Relationship::Friend,
+        Relationship::Food,
+        Relationship::Predator,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Relationship::Neutral => "Neutral",
+            Relationship::Friend => "Friend",
+            Relationship::Food => "Food",
+            Relationship::Predator => "Predator",
+        }
+    }
+
+    /// Interaction matrix coefficient the relationship stands for.
+    pub fn attraction(&self) -> f32 {
+        match self {
+            Relationship::Neutral => 0.0,
+            Relationship::Friend => 0.5,
+            Relationship::Food => 1.0,
+            Relationship::Predator => -1.0,
+        }
+    }
+}
+
+/// Template for one cell type.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Species {
+    pub name: String,
+    #[serde(with = "crate::scene::vec3_serde")]
+    pub color: Vec3, // RGB, 0-255 like `Circle::color`
+    pub radius: f32,
+    pub mass: f32,
+    pub speed_limit: f32,
+    #[serde(default)]
+    pub material: Material,
+    #[serde(default)]
+    pub bonding: Bonding,
+    /// How cells of this species decide where to go.
+    #[serde(default)]
+    pub behavior: BehaviorKind,
+    /// What cells of this species emit into and how they follow each
+    /// chemical channel, indexed by channel. Missing entries do neither.
+    #[serde(default)]
+    pub chemicals: Vec<ChemicalResponse>,
+    /// How this species treats every species, indexed by the other's cell
+    /// type. Missing entries count as neutral.
+    pub relationships: Vec<Relationship>,
+}
+
+impl Species {
+    pub fn new(name: &str, color: Vec3) -> Self {
+        Self {
+            name: name.to_string(),
+            color,
+            radius: 0.01,
+            mass: 1.0,
+            speed_limit: 0.0001,
+            material: Material::default(),
+            bonding: Bonding::default(),
+            behavior: BehaviorKind::default(),
+            chemicals: Vec::new(),
+            relationships: Vec::new(),
+        }
+    }
+
+    pub fn relationship(&self, cell_type: i32) -> Relationship {
+        if cell_type < 0 {
+            return Relationship::Neutral;
+        }
+        self.relationships.get(cell_type as usize).copied().unwrap_or_default()
+    }
+}
+
+/// Every species in a world. A species' index in the registry is the
+/// `cell_type` of its cells.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpeciesRegistry {
+    species: Vec<Species>,
+}
+
+impl Default for SpeciesRegistry {
+    /// The five species the sandbox has always shipped with.
+    fn default() -> Self {
+        use Relationship::*;
+        let mut registry = Self::empty();
+        // Aggressive, hunts food but keeps alliances
+        registry.add(Species::new("Red", vec3(225.0, 0.0, 0.0)));
+        // Defensive, gathers resources
+        registry.add(Species::new("Blue", vec3(0.0, 225.0, 0.0)));
+        // Dominant, preys on red and blue
+        registry.add(Species::new("White", vec3(0.0, 0.0, 255.0)));
+        // Neutral, supports others and neutralises white
+        registry.add(Species::new("Soil", vec3(0.0, 225.0, 255.0)));
+        // Essential resource with invasive tendencies
+        registry.add(Species::new("Food", vec3(255.0, 225.0, 0.0)));
+
+        let table = [
+            [Neutral, Friend, Predator, Friend, Food],
+            [Friend, Predator, Predator, Friend, Food],
+            [Food, Food, Friend, Predator, Friend],
+            [Friend, Friend, Food, Friend, Predator],
+            [Predator, Food, Friend, Predator, Friend],
+        ];
+        for (species, row) in registry.species.iter_mut().zip(table) {
+            species.relationships = row.to_vec();
+        }
+        registry
+    }
+}
+
+impl SpeciesRegistry {
+    pub fn empty() -> Self {
+        Self { species: Vec::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.species.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.species.is_empty()
+    }
+
+    pub fn get(&self, cell_type: i32) -> Option<&Species> {
+        if cell_type < 0 {
+            return None;
+        }
+        self.species.get(cell_type as usize)
+    }
+
+    pub fn get_mut(&mut self, cell_type: i32) -> Option<&mut Species> {
+        if cell_type < 0 {
+            return None;
+        }
+        self.species.get_mut(cell_type as usize)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Species> {
+        self.species.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Species> {
+        self.species.iter_mut()
+    }
+
+    /// Append a species and return its cell type.
+    pub fn add(&mut self, species: Species) -> i32 {
+        self.species.push(species);
+        self.species.len() as i32 - 1
+    }
+
+    /// Remove a species. Every later species moves down one cell type, and
+    /// relationships towards the removed one are dropped. Use
+    /// `World::remove_species` to keep the cells in step.
+    pub fn remove(&mut self, cell_type: i32) -> Option<Species> {
+        if cell_type < 0 || cell_type as usize >= self.species.len() {
+            return None;
+        }
+        let removed = self.species.remove(cell_type as usize);
+        for species in &mut self.species {
+            if (cell_type as usize) < species.relationships.len() {
+                species.relationships.remove(cell_type as usize);
+            }
+        }
+        Some(removed)
+    }
+
+    /// Interaction matrix implied by the relationships.
+    pub fn interactions(&self) -> InteractionMatrix {
+        let mut matrix = InteractionMatrix::new(self.species.len());
+        for (from, species) in self.species.iter().enumerate() {
+            for to in 0..self.species.len() {
+                let attraction = species.relationship(to as i32).attraction();
+                matrix.set(from as i32, to as i32, attraction);
+            }
+        }
+        matrix
+    }
+
+    /// Collision material of `cell_type`, or the default one for unknown types.
+    pub fn material(&self, cell_type: i32) -> Material {
+        self.get(cell_type).map_or_else(Material::default, |species| species.material)
+    }
+
+    /// Spring settings for a bond formed when cells of types `a` and `b`
+    /// touch, or `None` if they do not bond on contact. Both species have to
+    /// bond automatically and count each other as friends.
+    pub fn contact_bonding(&self, a: i32, b: i32) -> Option<Bonding> {
+        let (first, second) = (self.get(a)?, self.get(b)?);
+        let friends = first.relationship(b) == Relationship::Friend
+            && second.relationship(a) == Relationship::Friend;
+        let bonding = first.bonding.mix(&second.bonding);
+        if friends && bonding.automatic {
+            Some(bonding)
+        } else {
+            None
+        }
+    }
+
+    /// Cell types that `cell_type` eats.
+    pub fn diet(&self, cell_type: i32) -> Vec<i32> {
+        match self.get(cell_type) {
+            Some(species) => (0..self.species.len() as i32)
+                .filter(|&other| species.relationship(other) == Relationship::Food)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// A fresh cell of `cell_type` at `position`, built from the species'
+    /// defaults. Returns `None` for unknown types.
+    pub fn create(&self, cell_type: i32, position: Vec2) -> Option<Circle> {
+        let species = self.get(cell_type)?;
+        let mut circle = Circle::new(cell_type, position, species.radius, species.color, species.speed_limit);
+        circle.mass = species.mass;
+        circle.foods = self.diet(cell_type);
+        Some(circle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glm::vec2;
+
+    #[test]
+    fn relationships_drive_interactions_and_diet() {
+        let registry = SpeciesRegistry::default();
+        let interactions = registry.interactions();
+        assert_eq!(interactions.size(), 5);
+        assert_eq!(interactions.get(0, 4), Relationship::Food.attraction());
+        assert_eq!(interactions.get(0, 2), Relationship::Predator.attraction());
+        assert_eq!(registry.diet(2), vec![0, 1]);
+
+        let cell = registry.create(4, vec2(0.5, 0.5)).unwrap();
+        assert_eq!(cell.cell_type, 4);
+        assert_eq!(cell.color, registry.get(4).unwrap().color);
+        assert_eq!(cell.foods, registry.diet(4));
+        assert!(registry.create(5, vec2(0.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn removing_a_species_shifts_the_later_ones_down() {
+        let mut registry = SpeciesRegistry::default();
+        let removed = registry.remove(1).unwrap();
+        assert_eq!(removed.name, "Blue");
+        assert_eq!(registry.len(), 4);
+        assert_eq!(registry.get(1).unwrap().name, "White");
+        // White ate Red and Blue, now only Red is left to eat
+        assert_eq!(registry.diet(1), vec![0]);
+        assert!(registry.remove(4).is_none());
+    }
+}
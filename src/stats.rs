@@ -0,0 +1,221 @@
+use crate::species::SpeciesRegistry;
+use crate::world::World;
+use glm::{dot, length};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+/// Summary of a world at one moment.
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct WorldStats {
+    pub steps: u64,
+    /// Living cells per cell type, indexed by type. Cells whose type is not
+    /// in the species registry are only counted in `alive`.
+    pub population: Vec<usize>,
+    pub alive: usize,
+    pub corpses: usize,
+    /// Average speed of the living cells.
+    pub mean_speed: f32,
+    /// Energy stored in the living cells.
+    pub total_energy: f32,
+    pub mean_energy: f32,
+    /// Sum of ½mv² over the living cells.
+    pub kinetic_energy: f32,
+    /// Running totals, see `World::events`.
+    pub collisions: u64,
+    pub eaten: u64,
+}
+
+impl WorldStats {
+    pub fn measure(world: &World) -> Self {
+        let events = world.events();
+        let mut stats = WorldStats {
+            steps: world.steps(),
+            population: vec![0; world.species.len()],
+            collisions: events.collisions,
+            eaten: events.eaten,
+            ..Default::default()
+        };
+
+        let mut total_speed = 0.0;
+        for object in world.objects() {
+            if !object.is_alive() {
+                stats.corpses += 1;
+                continue;
+            }
+            stats.alive += 1;
+            if object.cell_type >= 0 {
+                if let Some(count) = stats.population.get_mut(object.cell_type as usize) {
+                    *count += 1;
+                }
+            }
+            total_speed += length(object.velocity);
+            stats.total_energy += object.energy;
+            stats.kinetic_energy += 0.5 * object.mass * dot(object.velocity, object.velocity);
+        }
+        if stats.alive > 0 {
+            stats.mean_speed = total_speed / stats.alive as f32;
+            stats.mean_energy = stats.total_energy / stats.alive as f32;
+        }
+        stats
+    }
+
+    /// Column names matching `csv_row`, with one population column per species.
+    pub fn csv_header(species: &SpeciesRegistry) -> String {
+        Self::csv_header_for(species.iter().map(|species| species.name.as_str()))
+    }
+
+    fn csv_header_for<'a>(names: impl Iterator<Item = &'a str>) -> String {
+        let mut header = String::from("step,alive,corpses");
+        for name in names {
+            // Keep the header parseable whatever the species are called
+            let name: String = name
+                .chars()
+                .map(|c| if c == ',' || c == '"' || c.is_whitespace() { '_' } else { c })
+                .collect();
+            let _ = write!(header, ",population_{}", name);
+        }
+        header.push_str(",mean_speed,total_energy,mean_energy,kinetic_energy,collisions,eaten");
+        header
+    }
+
+    pub fn csv_row(&self) -> String {
+        let mut row = format!("{},{},{}", self.steps, self.alive, self.corpses);
+        for count in &self.population {
+            let _ = write!(row, ",{}", count);
+        }
+        let _ = write!(
+            row,
+            ",{},{},{},{},{},{}",
+            self.mean_speed, self.total_energy, self.mean_energy, self.kinetic_energy, self.collisions, self.eaten
+        );
+        row
+    }
+}
+
+/// The most recent samples of a running world, oldest first.
+#[derive(Debug, Clone)]
+pub struct StatsHistory {
+    /// Samples kept before the oldest are dropped.
+    pub capacity: usize,
+    /// Names of the species the samples were counted under, which label the
+    /// population columns.
+    species: Vec<String>,
+    samples: VecDeque<WorldStats>,
+}
+
+impl Default for StatsHistory {
+    fn default() -> Self {
+        Self {
+            capacity: 10_000,
+            species: Vec::new(),
+            samples: VecDeque::new(),
+        }
+    }
+}
+
+impl StatsHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn samples(&self) -> impl Iterator<Item = &WorldStats> {
+        self.samples.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Add a sample taken from a world with `species`. A sample from an
+    /// earlier step than the newest one means the world was rewound or
+    /// replaced, and the stale future is dropped. Any change to the species
+    /// starts the history over, as the population columns would no longer
+    /// line up.
+    pub fn record(&mut self, stats: WorldStats, species: &SpeciesRegistry) {
+        let names = species.iter().map(|species| species.name.as_str());
+        if !self.species.iter().map(String::as_str).eq(names) {
+            self.samples.clear();
+            self.species = species.iter().map(|species| species.name.clone()).collect();
+        }
+        while self.samples.back().is_some_and(|last| last.steps >= stats.steps) {
+            self.samples.pop_back();
+        }
+        self.samples.push_back(stats);
+        while self.samples.len() > self.capacity.max(1) {
+            self.samples.pop_front();
+        }
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = WorldStats::csv_header_for(self.species.iter().map(String::as_str));
+        csv.push('\n');
+        for sample in &self.samples {
+            csv.push_str(&sample.csv_row());
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lifecycle::LifeStage;
+    use crate::objects::Circle;
+    use glm::{vec2, vec3};
+
+    #[test]
+    fn counts_living_cells_per_species_and_corpses_apart() {
+        let mut world = World::new();
+        for (cell_type, speed) in [(0, 0.2), (0, 0.4), (3, 0.0), (9, 0.0)] {
+            let mut cell = Circle::new(cell_type, vec2(0.0, 0.0), 0.01, vec3(1.0, 1.0, 1.0), 1.0);
+            cell.velocity = vec2(speed, 0.0);
+            cell.energy = 1.0;
+            world.spawn(cell);
+        }
+        let mut corpse = Circle::new(1, vec2(0.5, 0.5), 0.01, vec3(1.0, 1.0, 1.0), 1.0);
+        corpse.life = LifeStage::Corpse { remaining: 1.0 };
+        world.spawn(corpse);
+
+        let stats = WorldStats::measure(&world);
+        assert_eq!(stats.population, vec![2, 0, 0, 1, 0]);
+        assert_eq!(stats.alive, 4);
+        assert_eq!(stats.corpses, 1);
+        assert!((stats.mean_speed - 0.15).abs() < 1e-6);
+        assert_eq!(stats.total_energy, 4.0);
+    }
+
+    #[test]
+    fn history_drops_rewound_samples_and_starts_over_for_new_species() {
+        let mut world = World::new();
+        let mut history = StatsHistory::new();
+        history.capacity = 3;
+        for steps in [1, 2, 3, 4, 3] {
+            let stats = WorldStats {
+                steps,
+                ..WorldStats::measure(&world)
+            };
+            history.record(stats, &world.species);
+        }
+        let steps: Vec<u64> = history.samples().map(|sample| sample.steps).collect();
+        assert_eq!(steps, vec![2, 3]);
+
+        world.remove_species(0);
+        history.record(WorldStats::measure(&world), &world.species);
+        assert_eq!(history.len(), 1);
+        let csv = history.to_csv();
+        let mut lines = csv.lines();
+        let columns = lines.next().unwrap().split(',').count();
+        assert_eq!(columns, 3 + 4 + 6);
+        assert!(lines.all(|line| line.split(',').count() == columns));
+    }
+}
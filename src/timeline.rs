@@ -0,0 +1,220 @@
+use crate::bonds::Bond;
+use crate::chemistry::ChemicalResponse;
+use crate::emitters::{Emitter, Sink};
+use crate::objects::Circle;
+use crate::obstacles::Obstacle;
+use crate::scene::Scene;
+use crate::species::{Relationship, Species};
+use glm::Vec2;
+use std::collections::VecDeque;
+use std::mem::{size_of, size_of_val};
+
+/// Copy of the world taken by the timeline.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    scene: Scene,
+    bytes: usize,
+}
+
+impl Snapshot {
+    /// Number of steps the world had taken when the snapshot was made.
+    pub fn steps(&self) -> u64 {
+        self.scene.steps
+    }
+
+    pub fn scene(&self) -> &Scene {
+        &self.scene
+    }
+
+    /// Estimated heap and inline size, used against the memory budget.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+}
+
+/// Bounded ring buffer of periodic world snapshots to rewind to.
+///
+/// Once the snapshots outgrow `memory_budget` the oldest ones are dropped.
+/// Recording is off by default, since every snapshot is a full copy of the
+/// world.
+#[derive(Debug, Clone)]
+pub struct Timeline {
+    pub enabled: bool,
+    /// Steps between snapshots.
+    pub interval: u64,
+    /// Upper bound on the memory held by snapshots, in bytes. The newest
+    /// snapshot is always kept, even if it alone is over budget.
+    pub memory_budget: usize,
+    snapshots: VecDeque<Snapshot>,
+    bytes: usize,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: 30,
+            memory_budget: 64 * 1024 * 1024,
+            snapshots: VecDeque::new(),
+            bytes: 0,
+        }
+    }
+}
+
+impl Timeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    /// Memory currently held by snapshots, in bytes.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Oldest first.
+    pub fn snapshots(&self) -> impl Iterator<Item = &Snapshot> {
+        self.snapshots.iter()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Snapshot> {
+        self.snapshots.get(index)
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.bytes = 0;
+    }
+
+    /// Whether a world that has just taken its `steps`th step is due a snapshot.
+    pub fn is_due(&self, steps: u64) -> bool {
+        self.enabled && self.interval > 0 && steps % self.interval == 0
+    }
+
+    /// Store `scene` as the newest snapshot. Snapshots at or after its step
+    /// belong to a future that was rewound away from and are dropped first.
+    pub fn record(&mut self, scene: Scene) {
+        while self.snapshots.back().is_some_and(|last| last.steps() >= scene.steps) {
+            self.pop_back();
+        }
+
+        let bytes = scene_bytes(&scene);
+        self.bytes += bytes;
+        self.snapshots.push_back(Snapshot { scene, bytes });
+        self.trim();
+    }
+
+    /// Drop the oldest snapshots until the rest fit in the memory budget.
+    pub fn trim(&mut self) {
+        while self.bytes > self.memory_budget && self.snapshots.len() > 1 {
+            if let Some(oldest) = self.snapshots.pop_front() {
+                self.bytes -= oldest.bytes;
+            }
+        }
+    }
+
+    fn pop_back(&mut self) {
+        if let Some(newest) = self.snapshots.pop_back() {
+            self.bytes -= newest.bytes;
+        }
+    }
+}
+
+/// Estimate of the memory held by `scene`: every heap allocation it owns is
+/// counted, but allocator overhead and padding are not.
+fn scene_bytes(scene: &Scene) -> usize {
+    let circle_bytes = |object: &Circle| {
+        size_of::<Circle>()
+            + object.foods.capacity() * size_of::<i32>()
+            + object.genome.attraction.capacity() * size_of::<f32>()
+            + object.brain.as_ref().map_or(0, |brain| size_of_val(brain.weights()))
+    };
+    let species_bytes = |species: &Species| {
+        size_of::<Species>()
+            + species.name.capacity()
+            + species.relationships.capacity() * size_of::<Relationship>()
+            + species.chemicals.capacity() * size_of::<ChemicalResponse>()
+    };
+    let obstacle_bytes = |obstacle: &Obstacle| {
+        size_of::<Obstacle>()
+            + match obstacle {
+                Obstacle::Polygon { points } => points.capacity() * size_of::<Vec2>(),
+                _ => 0,
+            }
+    };
+    size_of::<Snapshot>()
+        + scene.objects.iter().map(circle_bytes).sum::<usize>()
+        + scene.species.iter().map(species_bytes).sum::<usize>()
+        + scene.obstacles.iter().map(obstacle_bytes).sum::<usize>()
+        + scene.interactions.size() * scene.interactions.size() * size_of::<f32>()
+        + scene.bonds.capacity() * size_of::<Bond>()
+        + scene
+            .chemistry
+            .channels()
+            .iter()
+            .map(|channel| size_of_val(channel.values()))
+            .sum::<usize>()
+        + scene.emitters.capacity() * size_of::<Emitter>()
+        + scene.sinks.capacity() * size_of::<Sink>()
+        + scene.default_object.as_ref().map_or(0, circle_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scene(steps: u64) -> Scene {
+        Scene {
+            steps,
+            ..Scene::default()
+        }
+    }
+
+    fn steps(timeline: &Timeline) -> Vec<u64> {
+        timeline.snapshots().map(Snapshot::steps).collect()
+    }
+
+    #[test]
+    fn snapshots_are_due_every_interval_once_enabled() {
+        let mut timeline = Timeline::new();
+        assert!(!timeline.is_due(30));
+        timeline.enabled = true;
+        assert!(timeline.is_due(30) && timeline.is_due(60));
+        assert!(!timeline.is_due(31));
+    }
+
+    #[test]
+    fn oldest_snapshots_are_trimmed_to_the_budget() {
+        let mut timeline = Timeline::new();
+        let size = scene_bytes(&scene(0));
+        timeline.memory_budget = size * 2;
+        for step in 1..=4 {
+            timeline.record(scene(step * 10));
+        }
+        assert_eq!(steps(&timeline), vec![30, 40]);
+        assert_eq!(timeline.bytes(), size * 2);
+
+        // The newest snapshot stays even when it alone is over budget
+        timeline.memory_budget = 1;
+        timeline.trim();
+        assert_eq!(steps(&timeline), vec![40]);
+    }
+
+    #[test]
+    fn recording_after_a_rewind_drops_the_old_future() {
+        let mut timeline = Timeline::new();
+        for step in 1..=3 {
+            timeline.record(scene(step * 10));
+        }
+        timeline.record(scene(20));
+        assert_eq!(steps(&timeline), vec![10, 20]);
+        assert_eq!(timeline.bytes(), timeline.snapshots().map(Snapshot::bytes).sum::<usize>());
+    }
+}
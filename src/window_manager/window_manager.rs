@@ -1,39 +1,114 @@
 pub mod windows {
     use ahash::{HashMap, HashMapExt};
-    use egui::{Modifiers, Ui};
-    use rand::Rng;
-    use crate::{objects, Circle};
+    use egui::{Color32, Modifiers, Ui};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha12Rng;
+    use egui_sdl2_gl::{
+        behavior::BehaviorKind,
+        bonds::Structure,
+        boundary::BoundaryMode,
+        brain::{ACTIONS, INPUTS, SENSORS},
+        chemistry::{Channel, ChemicalResponse},
+        emitters::{Emitter, Sink},
+        integrator::Integrator,
+        lifecycle::EntityId,
+        material::CombineRule,
+        objects::Circle,
+        obstacles::Obstacle,
+        scene::{Scene, SceneCamera, SceneError},
+        species::{Relationship, Species},
+        stats::{StatsHistory, WorldStats},
+        world::{BroadPhase, World},
+    };
     use glm::*;
 
     #[derive(Clone)]
     pub struct SandboxWindow {
-        pub objects: Vec<Circle>,
+        pub world: World,
         pub default_object: Circle,
         spawn_objects_count:i32,
+        seed: u64,
+        /// Kept in sync with the render camera by the main loop so it can be
+        /// saved with the scene and restored on load.
+        pub camera: SceneCamera,
+        scene_path: String,
+        scene_status: Option<String>,
+        paused: bool,
+        /// Timeline snapshot picked with the scrubber.
+        scrub: usize,
+        pub stats: StatsHistory,
+        stats_path: String,
+        stats_status: Option<String>,
+        /// Shape and cell type the "Spawn" button in the bonds section drops.
+        structure: Structure,
+        structure_type: i32,
+        /// Cells the "Bond" and "Unbond" buttons join or separate.
+        bond_cells: (u64, u64),
+        bond_status: Option<String>,
+        /// Draw the chemical channels under the cells.
+        pub show_chemicals: bool,
+        /// Cell shown in the brain inspector.
+        inspected: Option<EntityId>,
+        /// Keep the camera on the inspected cell.
+        follow_inspected: bool,
     }
 
     impl SandboxWindow {
         pub fn new() -> Self {
+            let mut world = World::new();
+            // Record a timeline to scrub through, plain library worlds don't
+            world.timeline.enabled = true;
             Self {
-                objects: [].to_vec(),
+                world,
                 default_object: Circle {
-                    position: vec2(0.5, 0.5),
-                    velocity: vec2(0.0, 0.0),
-                    radius: 0.01,
-                    mass: 1.0,
-                    color: vec3(225.0, 0.0, 0.0),
                     friction: 0.01,
-                    speed_limit: 0.0001,
-                    cell_type: 0,
-                    predators: [].to_vec(),
-                    friends: [].to_vec(),
-                    foods: [].to_vec(),
-                    hunger: 100.0,
+                    ..Circle::new(0, vec2(0.5, 0.5), 0.01, vec3(225.0, 0.0, 0.0), 0.0001)
                 },
                 spawn_objects_count: 10,
+                seed: 0,
+                camera: SceneCamera::default(),
+                scene_path: "scene.json".to_string(),
+                scene_status: None,
+                paused: false,
+                scrub: 0,
+                stats: StatsHistory::new(),
+                stats_path: "stats.csv".to_string(),
+                stats_status: None,
+                structure: Structure::Chain { length: 8 },
+                structure_type: 0,
+                bond_cells: (0, 0),
+                bond_status: None,
+                show_chemicals: true,
+                inspected: None,
+                follow_inspected: false,
             }
         }
 
+        pub fn save_scene(&self, path: &str) -> Result<(), SceneError> {
+            let mut scene = self.world.to_scene();
+            scene.default_object = Some(self.default_object.clone());
+            scene.camera = self.camera;
+            scene.save(path)
+        }
+
+        pub fn load_scene(&mut self, path: &str) -> Result<(), SceneError> {
+            let mut scene = Scene::load(path)?;
+            if let Some(default_object) = scene.default_object.take() {
+                self.default_object = default_object;
+            }
+            self.camera = scene.camera;
+            self.seed = scene.seed;
+            // Keep the timeline settings, the snapshots belong to the old world
+            let mut timeline = std::mem::take(&mut self.world.timeline);
+            timeline.clear();
+            // Registered behaviours live in code, not in the scene
+            let behaviors = std::mem::take(&mut self.world.behaviors);
+            self.world = World::from_scene(scene);
+            self.world.timeline = timeline;
+            self.world.behaviors = behaviors;
+            Ok(())
+        }
+
         pub fn ui(&mut self, ctx: &egui::Context, ui: &mut Ui) {
             let _ = ctx;
             self.scene_settings(ui);
@@ -46,6 +121,9 @@ pub mod windows {
             ui.horizontal(|ui| {
                 ui.label("Cell Type:");
                 ui.add(egui::DragValue::new(&mut self.default_object.cell_type).speed(1));
+                if let Some(species) = self.world.species.get(self.default_object.cell_type) {
+                    ui.label(&species.name);
+                }
             });
 
             // Position
@@ -94,92 +172,1000 @@ pub mod windows {
                 ui.add(egui::DragValue::new(&mut self.default_object.speed_limit).speed(0.01));
             });
 
-            // Add Circle Button
-            if ui.button("Generate World").clicked() {
-                for _ in 0..self.spawn_objects_count {
-                    let mut new_object = self.default_object.clone();
-                
-                    new_object.cell_type = rand::thread_rng().gen_range(1..=5); // Generates a number between 1 and 5 (inclusive)
-
-                    match new_object.cell_type {
-                        1 => {
-                            // red - Aggressive type, hunts for food, but can have alliances
-                            new_object.color = vec3(225.0, 0.0, 0.0);
-                            new_object.friends = vec![2, 4];  // Allies with blue and soil cells
-                            new_object.foods = vec![5];       // Hunts food cells
-                            new_object.predators = vec![3];   // Preyed upon by white cells
-                        }
-                        2 => {
-                            // blue - Defensive type, avoids conflict, gathers resources
-                            new_object.color = vec3(0.0, 225.0, 0.0);
-                            new_object.friends = vec![4, 1];  // Allies with soil and red cells
-                            new_object.foods = vec![5];       // Gathers food cells
-                            new_object.predators = vec![3,2];   // Preyed upon by white cells
+            // Energy
+            ui.horizontal(|ui| {
+                ui.label("Energy:");
+                ui.add(egui::DragValue::new(&mut self.default_object.energy).speed(1.0));
+            });
+
+            ui.separator();
+            ui.checkbox(&mut self.world.metabolism.enabled, "Metabolism");
+
+            ui.horizontal(|ui| {
+                ui.label("Basal Rate:");
+                ui.add(egui::DragValue::new(&mut self.world.metabolism.basal_rate).speed(0.01));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Movement Cost:");
+                ui.add(egui::DragValue::new(&mut self.world.metabolism.movement_cost).speed(0.1));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Energy per Mass:");
+                ui.add(egui::DragValue::new(&mut self.world.metabolism.energy_per_mass).speed(1.0));
+            });
+
+            ui.checkbox(&mut self.world.lifecycle.leave_corpses, "Leave Corpses");
+
+            ui.horizontal(|ui| {
+                ui.label("Corpse Lifetime (s):");
+                ui.add(egui::DragValue::new(&mut self.world.lifecycle.corpse_lifetime).speed(0.1));
+            });
+
+            ui.separator();
+            ui.checkbox(&mut self.world.reproduction.enabled, "Reproduction");
+
+            ui.horizontal(|ui| {
+                ui.label("Split Energy:");
+                ui.add(egui::DragValue::new(&mut self.world.reproduction.energy_threshold).speed(1.0));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Max Population:");
+                ui.add(egui::DragValue::new(&mut self.world.reproduction.max_population).speed(10));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Mutation Rate:");
+                ui.add(egui::DragValue::new(&mut self.world.reproduction.mutation_rate).speed(0.01).clamp_range(0.0..=1.0));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Mutation Scale:");
+                ui.add(egui::DragValue::new(&mut self.world.reproduction.mutation_scale).speed(0.01).clamp_range(0.0..=1.0));
+            });
+
+            let objects = self.world.objects();
+            let max_generation = objects.iter().map(|object| object.generation).max().unwrap_or(0);
+            let mut lineages: Vec<_> = objects.iter().map(|object| object.lineage).collect();
+            lineages.sort_unstable();
+            lineages.dedup();
+            ui.label(format!(
+                "Cells: {}  Lineages: {}  Max Generation: {}",
+                objects.len(),
+                lineages.len(),
+                max_generation
+            ));
+
+            ui.separator();
+            ui.label("Force Curve");
+
+            ui.horizontal(|ui| {
+                ui.label("Repulsion Radius:");
+                ui.add(egui::DragValue::new(&mut self.world.force_curve.repulsion_radius).speed(0.01));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Peak:");
+                ui.add(egui::DragValue::new(&mut self.world.force_curve.peak).speed(0.01));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Cutoff Radius:");
+                ui.add(egui::DragValue::new(&mut self.world.force_curve.cutoff_radius).speed(0.01));
+            });
+
+            ui.separator();
+            ui.label("Boundary");
+
+            ui.horizontal(|ui| {
+                ui.label("Mode:");
+                ui.selectable_value(&mut self.world.boundary.mode, BoundaryMode::Walls, "Walls");
+                ui.selectable_value(&mut self.world.boundary.mode, BoundaryMode::Wrap, "Wrap");
+                ui.selectable_value(&mut self.world.boundary.mode, BoundaryMode::Open, "Open");
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Min:");
+                ui.add(egui::DragValue::new(&mut self.world.boundary.min.x).speed(0.01).prefix("x:"));
+                ui.add(egui::DragValue::new(&mut self.world.boundary.min.y).speed(0.01).prefix("y:"));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Max:");
+                ui.add(egui::DragValue::new(&mut self.world.boundary.max.x).speed(0.01).prefix("x:"));
+                ui.add(egui::DragValue::new(&mut self.world.boundary.max.y).speed(0.01).prefix("y:"));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Wall Restitution:");
+                ui.add(egui::DragValue::new(&mut self.world.boundary.restitution).speed(0.01).clamp_range(0.0..=1.0));
+            });
+
+            if self.world.boundary.mode == BoundaryMode::Open {
+                ui.horizontal(|ui| {
+                    ui.label("Cull Distance:");
+                    ui.add(egui::DragValue::new(&mut self.world.boundary.cull_distance).speed(0.1).clamp_range(0.0..=f32::INFINITY))
+                        .on_hover_text("Remove cells this far outside the box, 0 keeps them all");
+                });
+            }
+
+            ui.separator();
+            ui.label("Obstacles");
+
+            ui.horizontal(|ui| {
+                if ui.button("Segment").clicked() {
+                    self.world.obstacles.push(Obstacle::Segment { start: vec2(-0.2, 0.0), end: vec2(0.2, 0.0) });
+                }
+                if ui.button("Box").clicked() {
+                    self.world.obstacles.push(Obstacle::Rect { min: vec2(-0.1, -0.1), max: vec2(0.1, 0.1) });
+                }
+                if ui.button("Disc").clicked() {
+                    self.world.obstacles.push(Obstacle::Disc { center: vec2(0.0, 0.0), radius: 0.1 });
+                }
+                if ui.button("Polygon").clicked() {
+                    let points = vec![vec2(-0.1, -0.1), vec2(0.1, -0.1), vec2(0.0, 0.1)];
+                    self.world.obstacles.push(Obstacle::Polygon { points });
+                }
+            });
+
+            let mut removed = None;
+            for (index, obstacle) in self.world.obstacles.iter_mut().enumerate() {
+                ui.push_id(index, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{} {}", obstacle.name(), index));
+                        if ui.button("🗑").on_hover_text("Remove obstacle").clicked() {
+                            removed = Some(index);
                         }
-                        3 => {
-                            // white - Dominant type, aggressive, preys on others
-                            new_object.color = vec3(0.0, 0.0, 255.0);
-                            new_object.friends = vec![3, 5];  // Allies with its own kind and food cells
-                            new_object.foods = vec![1, 2];    // Preys on red and blue cells
-                            new_object.predators = vec![4];   // Soil cells can neutralize it
+                    });
+                    match obstacle {
+                        Obstacle::Segment { start, end } => {
+                            point_row(ui, "Start:", start);
+                            point_row(ui, "End:", end);
                         }
-                        4 => {
-                            // soil - Neutral type, supports others, but can be defensive
-                            new_object.color = vec3(0.0, 225.0, 255.0);
-                            new_object.friends = vec![1, 2, 4];  // Allies with red, blue, and its own kind
-                            new_object.foods = vec![3];          // Can neutralize white cells
-                            new_object.predators = vec![5];      // Food cells can be invasive
+                        Obstacle::Rect { min, max } => {
+                            point_row(ui, "Min:", min);
+                            point_row(ui, "Max:", max);
+                            // Keep the box the right way round while dragging
+                            max.x = max.x.max(min.x);
+                            max.y = max.y.max(min.y);
                         }
-                        5 => {
-                            // food - Essential resource, tries to survive, invasive tendencies
-                            new_object.color = vec3(255.0, 225.0, 0.0);
-                            new_object.friends = vec![3, 5];  // Allies with white cells and its own kind
-                            new_object.foods = vec![2];       // Competes with blue cells
-                            new_object.predators = vec![1, 4];  // Preyed upon by red and soil cells
+                        Obstacle::Disc { center, radius } => {
+                            point_row(ui, "Center:", center);
+                            ui.horizontal(|ui| {
+                                ui.label("Radius:");
+                                ui.add(egui::DragValue::new(radius).speed(0.01).clamp_range(0.0..=f32::MAX));
+                            });
                         }
-                        _ => {
-                            // default - Unknown type, neutral behavior
-                            new_object.color = vec3(255.0, 255.0, 255.0);
+                        Obstacle::Polygon { points } => {
+                            let mut removed_point = None;
+                            for (i, point) in points.iter_mut().enumerate() {
+                                ui.horizontal(|ui| {
+                                    ui.label(format!("Point {}:", i));
+                                    ui.add(egui::DragValue::new(&mut point.x).speed(0.01).prefix("x:"));
+                                    ui.add(egui::DragValue::new(&mut point.y).speed(0.01).prefix("y:"));
+                                    if ui.small_button("-").on_hover_text("Remove point").clicked() {
+                                        removed_point = Some(i);
+                                    }
+                                });
+                            }
+                            if let Some(i) = removed_point {
+                                points.remove(i);
+                            }
+                            if ui.small_button("+").on_hover_text("Add a point after the last one").clicked() {
+                                let last = points.last().copied().unwrap_or(vec2(0.0, 0.0));
+                                points.push(last + vec2(0.05, 0.0));
+                            }
                         }
                     }
-                    
-                    new_object.position = vec2(
-                        rand::random::<f32>() * 0.8 - 0.1, // random x between 0.1 and 0.9
-                        rand::random::<f32>() * 0.8 - 0.1, // random y between 0.1 and 0.9
-                    );
-                    self.objects.push(new_object);
+                });
+            }
+            if let Some(index) = removed {
+                self.world.obstacles.remove(index);
+            }
+
+            ui.separator();
+            self.emitter_settings(ui);
+
+            ui.separator();
+
+            ui.label("Collisions");
+
+            ui.horizontal(|ui| {
+                ui.label("Combine:");
+                let combine = &mut self.world.collisions.combine;
+                ui.selectable_value(combine, CombineRule::Min, "Min");
+                ui.selectable_value(combine, CombineRule::Max, "Max");
+                ui.selectable_value(combine, CombineRule::Average, "Average");
+                ui.selectable_value(combine, CombineRule::Multiply, "Multiply");
+            });
+
+            override_row(ui, "Restitution:", &mut self.world.collisions.restitution_override, 0.5, 0.0..=1.0)
+                .on_hover_text("0 is perfectly inelastic, 1 perfectly elastic");
+            override_row(ui, "Friction:", &mut self.world.collisions.friction_override, 0.1, 0.0..=10.0);
+
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.world.collisions.continuous, "Continuous")
+                    .on_hover_text("Sweep fast cells along their path so they cannot tunnel through things");
+                ui.add_enabled(
+                    self.world.collisions.continuous,
+                    egui::DragValue::new(&mut self.world.collisions.max_impacts).prefix("max impacts/step: "),
+                );
+            });
+
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                ui.label("Broad Phase:");
+                ui.selectable_value(&mut self.world.broad_phase, BroadPhase::BruteForce, "Brute Force");
+                ui.selectable_value(&mut self.world.broad_phase, BroadPhase::SpatialHash, "Spatial Hash");
+            });
+
+            ui.separator();
+            ui.label("Time Step");
+
+            ui.horizontal(|ui| {
+                ui.label("Integrator:");
+                ui.selectable_value(&mut self.world.integrator, Integrator::SemiImplicitEuler, "Euler");
+                ui.selectable_value(&mut self.world.integrator, Integrator::VelocityVerlet, "Verlet");
+                ui.selectable_value(&mut self.world.integrator, Integrator::Rk4, "RK4");
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Step (s):");
+                ui.add(egui::DragValue::new(&mut self.world.time_step.dt).speed(0.001).clamp_range(0.0001..=0.1));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Substeps:");
+                ui.add(egui::DragValue::new(&mut self.world.time_step.substeps).speed(1).clamp_range(1..=32));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Max Catch-up:");
+                ui.add(egui::DragValue::new(&mut self.world.time_step.max_catch_up).speed(1).clamp_range(1..=60));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Threads:");
+                ui.add(egui::DragValue::new(&mut self.world.threads).speed(1).clamp_range(0..=256))
+                    .on_hover_text("0 uses every available core");
+            });
+
+            ui.separator();
+
+            ui.label("Timeline");
+
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.world.timeline.enabled, "Record");
+                ui.label("Every:");
+                ui.add(egui::DragValue::new(&mut self.world.timeline.interval).speed(1).clamp_range(1..=10_000).suffix(" steps"));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Memory Budget:");
+                let mut megabytes = self.world.timeline.memory_budget / (1024 * 1024);
+                if ui.add(egui::DragValue::new(&mut megabytes).speed(1).clamp_range(1..=16_384).suffix(" MB")).changed() {
+                    self.world.timeline.memory_budget = megabytes * 1024 * 1024;
+                    self.world.timeline.trim();
+                }
+            });
+            ui.label(format!(
+                "{} snapshots, {:.1} MB, step {}",
+                self.world.timeline.len(),
+                self.world.timeline.bytes() as f32 / (1024.0 * 1024.0),
+                self.world.steps(),
+            ));
+
+            ui.horizontal(|ui| {
+                if ui.button(if self.paused { "▶ Resume" } else { "⏸ Pause" }).clicked() {
+                    self.paused = !self.paused;
+                }
+                if ui.add_enabled(self.paused, egui::Button::new("⏭ Step")).on_hover_text("Run one fixed step").clicked() {
+                    self.world.fixed_step();
+                }
+            });
+
+            if !self.world.timeline.is_empty() {
+                let last = self.world.timeline.len() - 1;
+                self.scrub = self.scrub.min(last);
+                let response = ui.add(egui::Slider::new(&mut self.scrub, 0..=last).text("Snapshot"));
+                if let Some(snapshot) = self.world.timeline.get(self.scrub) {
+                    ui.weak(format!("Snapshot at step {}", snapshot.steps()));
+                }
+                if response.changed() {
+                    // Rewinding pauses so the picked moment can be inspected
+                    self.paused = true;
+                    self.world.rewind(self.scrub);
+                }
+            }
+
+            ui.separator();
+
+            // Seed
+            ui.horizontal(|ui| {
+                ui.label("Seed:");
+                ui.add(egui::DragValue::new(&mut self.seed).speed(1));
+                if ui.button("🎲").on_hover_text("Pick a random seed").clicked() {
+                    self.seed = rand::random();
                 }
+            });
+            ui.label(format!("World seed: {}", self.world.seed()));
+
+            ui.separator();
+            ui.label("Species");
+
+            let mut removed = None;
+            let mut brainless = None;
+            let mut relationships = Vec::new();
+            let names: Vec<String> = self.world.species.iter().map(|species| species.name.clone()).collect();
+            let channels: Vec<String> = self.world.chemistry.channels().iter().map(|channel| channel.name.clone()).collect();
+            let custom: Vec<String> = self.world.behaviors.names().into_iter().map(String::from).collect();
+            for (index, species) in self.world.species.iter_mut().enumerate() {
+                ui.push_id(("species", index), |ui| {
+                    ui.horizontal(|ui| {
+                        ui.add(egui::TextEdit::singleline(&mut species.name).desired_width(80.0));
+                        let mut color = [species.color.x / 255.0, species.color.y / 255.0, species.color.z / 255.0];
+                        if ui.color_edit_button_rgb(&mut color).changed() {
+                            species.color = vec3(color[0], color[1], color[2]) * 255.0;
+                        }
+                        if ui.button("🗑").on_hover_text("Remove the species and its cells").clicked() {
+                            removed = Some(index as i32);
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut species.radius).speed(0.001).clamp_range(0.001..=1.0).prefix("r:"));
+                        ui.add(egui::DragValue::new(&mut species.mass).speed(0.01).clamp_range(0.001..=f32::MAX).prefix("m:"));
+                        ui.add(egui::DragValue::new(&mut species.speed_limit).speed(0.0001).clamp_range(0.0..=f32::MAX).prefix("v:"));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut species.material.restitution).speed(0.01).clamp_range(0.0..=1.0).prefix("bounce:"));
+                        ui.add(egui::DragValue::new(&mut species.material.friction).speed(0.01).clamp_range(0.0..=f32::MAX).prefix("friction:"));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Behaviour:");
+                        egui::ComboBox::from_id_source("behavior")
+                            .selected_text(species.behavior.name())
+                            .show_ui(ui, |ui| {
+                                let builtins = BehaviorKind::builtins();
+                                let choices = builtins
+                                    .iter()
+                                    .cloned()
+                                    .chain(custom.iter().map(|name| BehaviorKind::Custom(name.clone())));
+                                for choice in choices {
+                                    let selected = species.behavior.same_kind(&choice);
+                                    if ui.selectable_label(selected, choice.name()).clicked() && !selected {
+                                        species.behavior = choice;
+                                    }
+                                }
+                            });
+                    });
+                    match &mut species.behavior {
+                        BehaviorKind::Boids(boids) => {
+                            ui.horizontal(|ui| {
+                                ui.add(egui::DragValue::new(&mut boids.radius).speed(0.01).clamp_range(0.0..=f32::MAX).prefix("radius:"));
+                                ui.add(egui::DragValue::new(&mut boids.separation).speed(0.01).prefix("separation:"));
+                            });
+                            ui.horizontal(|ui| {
+                                ui.add(egui::DragValue::new(&mut boids.alignment).speed(0.01).prefix("alignment:"));
+                                ui.add(egui::DragValue::new(&mut boids.cohesion).speed(0.01).prefix("cohesion:"));
+                            });
+                            ui.horizontal(|ui| {
+                                let mut limited = boids.field_of_view.is_some();
+                                if ui.checkbox(&mut limited, "Field of view").changed() {
+                                    boids.field_of_view = if limited { Some(std::f32::consts::PI * 1.5) } else { None };
+                                }
+                                if let Some(angle) = &mut boids.field_of_view {
+                                    ui.drag_angle(angle);
+                                    *angle = angle.clamp(0.0, std::f32::consts::PI * 2.0);
+                                }
+                            });
+                            ui.add(egui::DragValue::new(&mut boids.reactions).speed(0.01).prefix("predators & food:"))
+                                .on_hover_text("Weight of fleeing predators and chasing food");
+                        }
+                        BehaviorKind::Wanderer(wanderer) => {
+                            ui.horizontal(|ui| {
+                                ui.add(egui::DragValue::new(&mut wanderer.strength).speed(0.01).clamp_range(0.0..=f32::MAX).prefix("strength:"));
+                                ui.label("Turn:");
+                                ui.drag_angle(&mut wanderer.jitter);
+                            });
+                        }
+                        BehaviorKind::Neural(neural) => {
+                            ui.horizontal(|ui| {
+                                ui.add(egui::DragValue::new(&mut neural.strength).speed(0.01).clamp_range(0.0..=f32::MAX).prefix("strength:"));
+                                ui.add(egui::DragValue::new(&mut neural.hidden).clamp_range(1..=64).prefix("hidden:"))
+                                    .on_hover_text("Hidden neurons in brains grown from now on");
+                                if ui.button("Grow Brains").on_hover_text("Give every cell of the species without a brain a random one").clicked() {
+                                    brainless = Some((index as i32, neural.hidden));
+                                }
+                            });
+                        }
+                        _ => {}
+                    }
+                    ui.collapsing("Bonding", |ui| {
+                        let bonding = &mut species.bonding;
+                        ui.checkbox(&mut bonding.automatic, "Bond to touching friends");
+                        ui.horizontal(|ui| {
+                            ui.add(egui::DragValue::new(&mut bonding.max_bonds).clamp_range(0..=12).prefix("max:"));
+                            ui.add(egui::DragValue::new(&mut bonding.stiffness).speed(0.1).clamp_range(0.0..=f32::MAX).prefix("k:"));
+                            ui.add(egui::DragValue::new(&mut bonding.damping).speed(0.01).clamp_range(0.0..=f32::MAX).prefix("damping:"));
+                        });
+                        ui.add(egui::DragValue::new(&mut bonding.break_force).speed(0.01).clamp_range(0.0..=f32::MAX).prefix("break at:"))
+                            .on_hover_text("Spring force that snaps the bond, 0 never breaks");
+                    });
+                    if !channels.is_empty() {
+                        ui.collapsing("Chemicals", |ui| {
+                            if species.chemicals.len() < channels.len() {
+                                species.chemicals.resize(channels.len(), ChemicalResponse::default());
+                            }
+                            for (response, name) in species.chemicals.iter_mut().zip(&channels) {
+                                ui.horizontal(|ui| {
+                                    ui.add(egui::DragValue::new(&mut response.emit).speed(0.01).prefix("emit:"))
+                                        .on_hover_text("Amount deposited per second, negative values consume");
+                                    ui.add(egui::DragValue::new(&mut response.taxis).speed(0.01).prefix("taxis:"))
+                                        .on_hover_text("Pull up the gradient, negative values flee it");
+                                    ui.label(name);
+                                });
+                            }
+                        });
+                    }
+                    ui.collapsing("Relationships", |ui| {
+                        for (other, name) in names.iter().enumerate() {
+                            let current = species.relationship(other as i32);
+                            let mut selected = current;
+                            ui.horizontal(|ui| {
+                                egui::ComboBox::from_id_source(("relationship", other))
+                                    .selected_text(selected.name())
+                                    .show_ui(ui, |ui| {
+                                        for relationship in Relationship::ALL {
+                                            ui.selectable_value(&mut selected, relationship, relationship.name());
+                                        }
+                                    });
+                                ui.label(name);
+                            });
+                            if selected != current {
+                                relationships.push((index as i32, other as i32, selected));
+                            }
+                        }
+                    });
+                });
+            }
+            if let Some(cell_type) = removed {
+                self.world.remove_species(cell_type);
+            }
+            if let Some((cell_type, hidden)) = brainless {
+                self.world.grow_brains(cell_type, hidden);
+            }
+            for (from, to, relationship) in relationships {
+                self.world.set_relationship(from, to, relationship);
+            }
+            if ui.button("Add Species").clicked() {
+                let name = format!("Species {}", self.world.species.len() + 1);
+                // Keep the world's RNG for the simulation itself
+                let color = vec3(rand::random(), rand::random(), rand::random()) * 255.0;
+                self.world.species.add(Species::new(&name, color));
+                self.world.apply_species();
+            }
+
+            ui.separator();
+            self.bond_settings(ui);
+
+            ui.separator();
+            self.chemistry_settings(ui);
+
+            ui.separator();
+            self.interaction_settings(ui);
+
+            ui.separator();
+
+            if ui.button("Generate World").on_hover_text("Replace the world with one generated from the seed").clicked() {
+                let count = Ord::max(self.spawn_objects_count, 0) as usize;
+                self.world.generate(self.seed, count, &self.default_object);
             }
             if ui.button("Add Circle").clicked(){
-                self.objects.push(self.default_object.clone());
+                self.world.spawn(self.default_object.clone());
+            }
+        }
+
+        /// Placeable sources that spawn cells and sinks that remove them.
+        pub fn emitter_settings(&mut self, ui: &mut Ui) {
+            ui.label("Emitters & Sinks");
+
+            ui.horizontal(|ui| {
+                if ui.button("Emitter").clicked() {
+                    self.world.emitters.push(Emitter::new(0, self.camera.offset));
+                }
+                if ui.button("Sink").clicked() {
+                    self.world.sinks.push(Sink { position: self.camera.offset, ..Sink::default() });
+                }
+            });
+
+            let names: Vec<String> = self.world.species.iter().map(|species| species.name.clone()).collect();
+            let mut removed = None;
+            for (index, emitter) in self.world.emitters.iter_mut().enumerate() {
+                ui.push_id(("emitter", index), |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("Emitter {}", index));
+                        let name = names.get(emitter.cell_type as usize).map_or("None", String::as_str);
+                        egui::ComboBox::from_id_source("emitter_type")
+                            .selected_text(name)
+                            .show_ui(ui, |ui| {
+                                for (cell_type, name) in names.iter().enumerate() {
+                                    ui.selectable_value(&mut emitter.cell_type, cell_type as i32, name);
+                                }
+                            });
+                        if ui.button("🗑").on_hover_text("Remove emitter").clicked() {
+                            removed = Some(index);
+                        }
+                    });
+                    point_row(ui, "Position:", &mut emitter.position);
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut emitter.rate).speed(0.1).clamp_range(0.0..=f32::MAX).suffix(" /s"));
+                        ui.add(egui::DragValue::new(&mut emitter.spread).speed(0.01).clamp_range(0.0..=f32::MAX).prefix("spread:"));
+                        ui.add(egui::DragValue::new(&mut emitter.limit).prefix("limit:"))
+                            .on_hover_text("Pause while this many of its cells are within the spread, 0 never pauses");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Heading:");
+                        ui.drag_angle(&mut emitter.direction);
+                        ui.label("±");
+                        ui.drag_angle(&mut emitter.angle_spread);
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Speed:");
+                        ui.add(egui::DragValue::new(&mut emitter.min_speed).speed(0.0001).clamp_range(0.0..=f32::MAX));
+                        ui.label("to");
+                        ui.add(egui::DragValue::new(&mut emitter.max_speed).speed(0.0001).clamp_range(0.0..=f32::MAX));
+                    });
+                });
             }
+            if let Some(index) = removed {
+                self.world.emitters.remove(index);
+            }
+
+            let mut removed = None;
+            for (index, sink) in self.world.sinks.iter_mut().enumerate() {
+                ui.push_id(("sink", index), |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("Sink {}", index));
+                        ui.add(egui::DragValue::new(&mut sink.radius).speed(0.01).clamp_range(0.0..=f32::MAX).prefix("r:"));
+                        if ui.button("🗑").on_hover_text("Remove sink").clicked() {
+                            removed = Some(index);
+                        }
+                    });
+                    point_row(ui, "Position:", &mut sink.position);
+                });
+            }
+            if let Some(index) = removed {
+                self.world.sinks.remove(index);
+            }
+
+            let events = self.world.events();
+            ui.label(format!("Emitted: {}  Absorbed: {}", events.emitted, events.absorbed));
         }
 
-        pub fn update_objects(&mut self, dt: f32, window_width: f32, window_height: f32) {
-            // Create a separate vector to hold updated objects
-            let mut updated_objects = Vec::new();
+        /// Bond count and a spawner for ready-made bonded shapes. Springs
+        /// take their settings from the species.
+        pub fn bond_settings(&mut self, ui: &mut Ui) {
+            ui.horizontal(|ui| {
+                ui.label(format!("Bonds: {}", self.world.bonds.len()));
+                if ui.button("Clear Bonds").clicked() {
+                    self.world.bonds.clear();
+                }
+            });
 
-            for object in &mut self.objects {
-                object.update(dt);
-                updated_objects.push(object.clone());
+            ui.horizontal(|ui| {
+                if ui.button("Nearest").on_hover_text("Pick the two cells nearest the middle of the view").clicked() {
+                    let centre = self.camera.offset;
+                    let distance = |object: &&Circle| length(self.world.boundary.delta(centre, object.position));
+                    let mut nearest: Vec<&Circle> = self.world.objects().iter().filter(|object| object.is_alive()).collect();
+                    nearest.sort_by(|a, b| distance(a).total_cmp(&distance(b)));
+                    if let [a, b, ..] = nearest[..] {
+                        self.bond_cells = (a.id.0, b.id.0);
+                    }
+                }
+                ui.add(egui::DragValue::new(&mut self.bond_cells.0).speed(1).prefix("a:"));
+                ui.add(egui::DragValue::new(&mut self.bond_cells.1).speed(1).prefix("b:"));
+                let (a, b) = (EntityId(self.bond_cells.0), EntityId(self.bond_cells.1));
+                if ui.button("Bond").on_hover_text("Join the two cells at their current distance").clicked() {
+                    self.bond_status = if self.world.bond(a, b) {
+                        None
+                    } else {
+                        Some("Missing cell, same cell or already bonded".to_string())
+                    };
+                }
+                if ui.button("Unbond").clicked() {
+                    self.bond_status = if self.world.unbond(a, b) {
+                        None
+                    } else {
+                        Some("These cells are not bonded".to_string())
+                    };
+                }
+            });
+            if let Some(status) = &self.bond_status {
+                ui.weak(status);
             }
 
-            // Now handle border collisions for each updated object
-            for object in &mut updated_objects {
-                // self.handle_border_collision(object, window_width, window_height);
-                object.decide(&mut self.objects);
+            ui.horizontal(|ui| {
+                let structure = &mut self.structure;
+                for shape in [
+                    Structure::Chain { length: 8 },
+                    Structure::Ring { length: 12 },
+                    Structure::SoftBody { columns: 4, rows: 4 },
+                ] {
+                    let selected = std::mem::discriminant(structure) == std::mem::discriminant(&shape);
+                    if ui.selectable_label(selected, shape.name()).clicked() && !selected {
+                        *structure = shape;
+                    }
+                }
+            });
+
+            ui.horizontal(|ui| {
+                match &mut self.structure {
+                    Structure::Chain { length } | Structure::Ring { length } => {
+                        ui.add(egui::DragValue::new(length).clamp_range(1..=200).prefix("cells:"));
+                    }
+                    Structure::SoftBody { columns, rows } => {
+                        ui.add(egui::DragValue::new(columns).clamp_range(1..=30).prefix("columns:"));
+                        ui.add(egui::DragValue::new(rows).clamp_range(1..=30).prefix("rows:"));
+                    }
+                }
+                let name = self
+                    .world
+                    .species
+                    .get(self.structure_type)
+                    .map_or("None", |species| species.name.as_str())
+                    .to_string();
+                egui::ComboBox::from_id_source("structure_type")
+                    .selected_text(name)
+                    .show_ui(ui, |ui| {
+                        for (cell_type, species) in self.world.species.iter().enumerate() {
+                            ui.selectable_value(&mut self.structure_type, cell_type as i32, &species.name);
+                        }
+                    });
+                if ui.button("Spawn").on_hover_text("Drop it in the middle of the view").clicked() {
+                    self.world.spawn_structure(self.structure_type, self.camera.offset, self.structure);
+                }
+            });
+        }
+
+        /// Chemical channels and the grid they live on. What each species
+        /// emits and follows is set in the species panel.
+        pub fn chemistry_settings(&mut self, ui: &mut Ui) {
+            ui.horizontal(|ui| {
+                ui.label("Chemicals");
+                ui.checkbox(&mut self.show_chemicals, "Show");
+            });
+
+            let chemistry = &mut self.world.chemistry;
+            ui.horizontal(|ui| {
+                let (mut columns, mut rows) = (chemistry.columns(), chemistry.rows());
+                ui.label("Grid:");
+                let changed = ui.add(egui::DragValue::new(&mut columns).clamp_range(1..=512).suffix(" columns")).changed()
+                    | ui.add(egui::DragValue::new(&mut rows).clamp_range(1..=512).suffix(" rows")).changed();
+                if changed {
+                    chemistry.set_resolution(columns, rows);
+                }
+                if ui.button("Clear").on_hover_text("Empty every channel").clicked() {
+                    chemistry.clear();
+                }
+            });
+
+            let mut removed = None;
+            for (index, channel) in chemistry.channels_mut().iter_mut().enumerate() {
+                ui.push_id(("channel", index), |ui| {
+                    ui.horizontal(|ui| {
+                        ui.add(egui::TextEdit::singleline(&mut channel.name).desired_width(80.0));
+                        let mut color = [channel.color.x / 255.0, channel.color.y / 255.0, channel.color.z / 255.0];
+                        if ui.color_edit_button_rgb(&mut color).changed() {
+                            channel.color = vec3(color[0], color[1], color[2]) * 255.0;
+                        }
+                        if ui.button("🗑").on_hover_text("Remove the channel").clicked() {
+                            removed = Some(index);
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut channel.diffusion).speed(0.01).clamp_range(0.0..=f32::MAX).prefix("diffusion:"));
+                        ui.add(egui::DragValue::new(&mut channel.evaporation).speed(0.01).clamp_range(0.0..=f32::MAX).prefix("evaporation:"));
+                    });
+                });
+            }
+            if let Some(index) = removed {
+                self.world.remove_channel(index);
+            }
+            if ui.button("Add Channel").clicked() {
+                let name = format!("Chemical {}", self.world.chemistry.channels().len() + 1);
+                let color = vec3(rand::random(), rand::random(), rand::random()) * 255.0;
+                self.world.chemistry.add(Channel::new(&name, color));
+            }
+        }
+
+        /// Grid of attraction coefficients, one row per cell type and one
+        /// column per type it reacts to. Edits apply to the running world.
+        pub fn interaction_settings(&mut self, ui: &mut Ui) {
+            ui.label("Interactions");
+
+            ui.horizontal(|ui| {
+                if ui.button("Randomize").clicked() {
+                    // Keep the world's RNG for the simulation itself
+                    let mut rng = ChaCha12Rng::seed_from_u64(rand::random());
+                    self.world.interactions.randomize(&mut rng);
+                }
+                if ui.button("Symmetrize").clicked() {
+                    self.world.interactions.symmetrize();
+                }
+                if ui.button("Zero").clicked() {
+                    self.world.interactions.zero();
+                }
+                if ui.button("Invert").clicked() {
+                    self.world.interactions.invert();
+                }
+            });
+
+            let types = self.world.species.len();
+            if self.world.interactions.size() < types {
+                self.world.interactions.resize(types);
             }
 
-            // Replace the objects with the updated objects
-            self.objects = updated_objects;
-            // Handle collisions between circles
-            handle_collisions(&mut self.objects);
+            egui::Grid::new("interaction_matrix").spacing([2.0, 2.0]).show(ui, |ui| {
+                ui.label("").on_hover_text("Rows are drawn towards columns");
+                for species in self.world.species.iter() {
+                    let short: String = species.name.chars().take(3).collect();
+                    ui.colored_label(species_color(species.color), short).on_hover_text(&species.name);
+                }
+                ui.end_row();
+
+                for from in 0..types as i32 {
+                    if let Some(species) = self.world.species.get(from) {
+                        ui.colored_label(species_color(species.color), &species.name);
+                    }
+                    for to in 0..types as i32 {
+                        let mut value = self.world.interactions.get(from, to);
+                        let fill = attraction_color(value);
+                        let response = ui
+                            .scope(|ui| {
+                                let widgets = &mut ui.visuals_mut().widgets;
+                                widgets.inactive.weak_bg_fill = fill;
+                                widgets.hovered.weak_bg_fill = fill;
+                                widgets.active.weak_bg_fill = fill;
+                                ui.add(
+                                    egui::DragValue::new(&mut value)
+                                        .speed(0.01)
+                                        .clamp_range(-1.0..=1.0)
+                                        .max_decimals(2),
+                                )
+                            })
+                            .inner;
+                        if response.changed() {
+                            self.world.interactions.set(from, to, value);
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
         }
-    
+
+        /// Time series of the recorded statistics.
+        pub fn stats_ui(&mut self, ui: &mut Ui) {
+            use egui_plot::{Legend, Line, Plot, PlotPoints};
+
+            ui.horizontal(|ui| {
+                ui.label("History:");
+                ui.add(egui::DragValue::new(&mut self.stats.capacity).speed(100).clamp_range(10..=1_000_000).suffix(" samples"));
+                if ui.button("Clear").clicked() {
+                    self.stats.clear();
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut self.stats_path);
+                if ui.button("Export CSV").clicked() {
+                    let csv = self.stats.to_csv();
+                    self.stats_status = Some(match std::fs::write(&self.stats_path, csv) {
+                        Ok(()) => format!("Wrote {} samples to {}", self.stats.len(), self.stats_path),
+                        Err(error) => format!("Could not write {}: {}", self.stats_path, error),
+                    });
+                }
+            });
+            if let Some(status) = &self.stats_status {
+                ui.weak(status);
+            }
+
+            let series = |value: &dyn Fn(&WorldStats) -> f64| -> PlotPoints {
+                self.stats.samples().map(|sample| [sample.steps as f64, value(sample)]).collect()
+            };
+            // Events are running totals, plot them as rates instead
+            let seconds_per_step = (self.world.time_step.dt / Ord::max(self.world.time_step.substeps, 1) as f32) as f64;
+            let rate = |count: &dyn Fn(&WorldStats) -> u64| -> PlotPoints {
+                let samples: Vec<&WorldStats> = self.stats.samples().collect();
+                samples
+                    .windows(2)
+                    .map(|pair| {
+                        let steps = (pair[1].steps - pair[0].steps) as f64;
+                        let events = count(pair[1]).saturating_sub(count(pair[0])) as f64;
+                        [pair[1].steps as f64, events / (steps * seconds_per_step)]
+                    })
+                    .collect()
+            };
+            let height = 120.0;
+
+            ui.label("Population");
+            Plot::new("population_plot").height(height).legend(Legend::default()).show(ui, |plot_ui| {
+                for (cell_type, species) in self.world.species.iter().enumerate() {
+                    let points = series(&|sample| sample.population.get(cell_type).copied().unwrap_or(0) as f64);
+                    plot_ui.line(Line::new(points).name(&species.name).color(species_color(species.color)));
+                }
+            });
+
+            ui.label("Energy");
+            Plot::new("energy_plot").height(height).legend(Legend::default()).show(ui, |plot_ui| {
+                plot_ui.line(Line::new(series(&|sample| sample.mean_energy as f64)).name("Mean energy"));
+                plot_ui.line(Line::new(series(&|sample| sample.kinetic_energy as f64)).name("Kinetic energy"));
+            });
+
+            ui.label("Events per second");
+            Plot::new("events_plot").height(height).legend(Legend::default()).show(ui, |plot_ui| {
+                plot_ui.line(Line::new(rate(&|sample| sample.eaten)).name("Eaten"));
+                plot_ui.line(Line::new(rate(&|sample| sample.collisions)).name("Collisions"));
+            });
+        }
+
+        /// Live view of one cell's brain: what its sensors read, how its
+        /// neurons respond and what it does about it.
+        pub fn brain_ui(&mut self, ui: &mut Ui) {
+            ui.horizontal(|ui| {
+                if ui.button("Nearest").on_hover_text("Inspect the cell with a brain nearest the middle of the view").clicked() {
+                    let centre = self.camera.offset;
+                    let distance = |object: &&Circle| length(self.world.boundary.delta(centre, object.position));
+                    self.inspected = self
+                        .world
+                        .objects()
+                        .iter()
+                        .filter(|object| object.brain.is_some() && object.is_alive())
+                        .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+                        .map(|object| object.id);
+                }
+                ui.checkbox(&mut self.follow_inspected, "Follow");
+            });
+
+            let (cell, activations) = match self.inspected.and_then(|id| Some((self.world.get(id)?, self.world.think(id)?))) {
+                Some(found) => found,
+                None => {
+                    ui.weak("No cell with a brain picked");
+                    return;
+                }
+            };
+            let brain = match &cell.brain {
+                Some(brain) => brain,
+                None => return,
+            };
+            let name = self.world.species.get(cell.cell_type).map_or("Unknown", |species| species.name.as_str());
+            ui.label(format!(
+                "Cell {} ({}), generation {}, energy {:.1}",
+                cell.id.0, name, cell.generation, cell.energy
+            ));
+            let outputs: Vec<String> = ACTIONS
+                .iter()
+                .zip(activations.outputs)
+                .map(|(action, value)| format!("{} {:+.2}", action, value))
+                .collect();
+            ui.label(outputs.join("  "));
+
+            // Inputs on the left with their names, then the hidden layer and
+            // the outputs. Edges are brighter the more signal they carry.
+            let height = 16.0 * INPUTS as f32;
+            let (response, painter) = ui.allocate_painter(egui::vec2(ui.available_width(), height), egui::Sense::hover());
+            let rect = response.rect;
+            let node = |fraction: f32, index: usize, count: usize| {
+                let spacing = height / count.max(1) as f32;
+                egui::pos2(rect.left() + rect.width() * fraction, rect.top() + spacing * (index as f32 + 0.5))
+            };
+            let edge = |weight: f32, signal: f32| {
+                let strength = (weight * signal).abs().min(1.0);
+                let alpha = (40.0 + strength * 215.0) as u8;
+                let color = if weight >= 0.0 {
+                    Color32::from_rgba_unmultiplied(80, 200, 80, alpha)
+                } else {
+                    Color32::from_rgba_unmultiplied(200, 80, 80, alpha)
+                };
+                egui::Stroke::new(0.5 + strength * 1.5, color)
+            };
+            let hidden = brain.hidden();
+            let (inputs_at, hidden_at, outputs_at) = (0.5, 0.72, 0.94);
+            for neuron in 0..hidden {
+                for input in 0..INPUTS {
+                    let stroke = edge(brain.input_weight(neuron, input), activations.inputs[input]);
+                    painter.line_segment([node(inputs_at, input, INPUTS), node(hidden_at, neuron, hidden)], stroke);
+                }
+                for output in 0..ACTIONS.len() {
+                    let stroke = edge(brain.output_weight(output, neuron), activations.hidden[neuron]);
+                    painter.line_segment([node(hidden_at, neuron, hidden), node(outputs_at, output, ACTIONS.len())], stroke);
+                }
+            }
+            let outline = egui::Stroke::new(1.0, Color32::GRAY);
+            let layers = [
+                (inputs_at, &activations.inputs[..]),
+                (hidden_at, &activations.hidden[..]),
+                (outputs_at, &activations.outputs[..]),
+            ];
+            for (fraction, values) in layers {
+                for (index, value) in values.iter().enumerate() {
+                    painter.circle(node(fraction, index, values.len()), 5.0, attraction_color(*value), outline);
+                }
+            }
+            for (input, sensor) in SENSORS.iter().enumerate() {
+                painter.text(
+                    node(inputs_at, input, INPUTS) - egui::vec2(10.0, 0.0),
+                    egui::Align2::RIGHT_CENTER,
+                    format!("{} {:+.2}", sensor, activations.inputs[input]),
+                    egui::FontId::proportional(11.0),
+                    ui.visuals().text_color(),
+                );
+            }
+
+            if self.follow_inspected {
+                self.camera.offset = cell.position;
+            }
+        }
+
+        pub fn update_objects(&mut self, dt: f32) {
+            if !self.paused {
+                self.world.advance(dt);
+            }
+            self.stats.record(WorldStats::measure(&self.world), &self.world.species);
+        }
+    }
+
+    fn species_color(color: Vec3) -> Color32 {
+        let channel = |value: f32| value.clamp(0.0, 255.0) as u8;
+        Color32::from_rgb(channel(color.x), channel(color.y), channel(color.z))
+    }
+
+    // Green for attraction, red for repulsion, brighter the stronger it is.
+    fn attraction_color(value: f32) -> Color32 {
+        let strength = (value.abs().min(1.0) * 160.0) as u8;
+        if value >= 0.0 {
+            Color32::from_rgb(40, 40 + strength, 40)
+        } else {
+            Color32::from_rgb(40 + strength, 40, 40)
+        }
+    }
+
+    // Checkbox that switches a global override on, plus its value.
+    fn override_row(
+        ui: &mut Ui,
+        label: &str,
+        value: &mut Option<f32>,
+        default: f32,
+        range: std::ops::RangeInclusive<f32>,
+    ) -> egui::Response {
+        ui.horizontal(|ui| {
+            let mut enabled = value.is_some();
+            ui.checkbox(&mut enabled, label);
+            match (enabled, value.as_mut()) {
+                (true, Some(current)) => {
+                    ui.add(egui::DragValue::new(current).speed(0.01).clamp_range(range));
+                }
+                (true, None) => *value = Some(default),
+                (false, _) => *value = None,
+            }
+        })
+        .response
+    }
+
+    fn point_row(ui: &mut Ui, label: &str, point: &mut Vec2) {
+        ui.horizontal(|ui| {
+            ui.label(label);
+            ui.add(egui::DragValue::new(&mut point.x).speed(0.01).prefix("x:"));
+            ui.add(egui::DragValue::new(&mut point.y).speed(0.01).prefix("y:"));
+        });
     }
 
     pub struct MainWindow<'a> {
         pub show_sandbox_window: bool,
+        pub show_stats_window: bool,
+        pub show_brain_window: bool,
         pub sandbox_window: &'a mut SandboxWindow,
     }
 
@@ -187,6 +1173,8 @@ pub mod windows {
         pub fn new(sandbox_window: &'a mut SandboxWindow) -> Self {
             Self {
                 show_sandbox_window: false,
+                show_stats_window: false,
+                show_brain_window: false,
                 sandbox_window,
             }
         }
@@ -213,6 +1201,22 @@ pub mod windows {
                                 self.sandbox_window.ui(ctx, ui);
                             });
                     }
+                    if self.show_stats_window {
+                        egui::Window::new("Statistics")
+                            .resizable(true)
+                            .default_width(400.0)
+                            .show(ctx, |ui| {
+                                self.sandbox_window.stats_ui(ui);
+                            });
+                    }
+                    if self.show_brain_window {
+                        egui::Window::new("Brain Inspector")
+                            .resizable(true)
+                            .default_width(400.0)
+                            .show(ctx, |ui| {
+                                self.sandbox_window.brain_ui(ui);
+                            });
+                    }
                     ui.hyperlink_to(
                         format!("{GITHUB} Resource Code"),
                         "https://github.com/OmarDevX",
@@ -223,7 +1227,7 @@ pub mod windows {
 
             egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
                 egui::menu::bar(ui, |ui| {
-                    file_menu_button(ui);
+                    file_menu_button(ui, self.sandbox_window);
                 });
             });
         }
@@ -236,6 +1240,14 @@ pub mod windows {
                         self.show_sandbox_window = !self.show_sandbox_window;
                     }
 
+                    if ui.button("Statistics").clicked() {
+                        self.show_stats_window = !self.show_stats_window;
+                    }
+
+                    if ui.button("Brain Inspector").clicked() {
+                        self.show_brain_window = !self.show_brain_window;
+                    }
+
                     if ui.button("Organize windows").clicked() {
                         ui.ctx().memory_mut(|mem| mem.reset_areas());
                     }
@@ -244,7 +1256,7 @@ pub mod windows {
         }
     }
 
-    pub fn file_menu_button(ui: &mut Ui) {
+    pub fn file_menu_button(ui: &mut Ui, sandbox_window: &mut SandboxWindow) {
         let organize_shortcut =
             egui::KeyboardShortcut::new(Modifiers::CTRL | Modifiers::SHIFT, egui::Key::O);
         let reset_shortcut =
@@ -262,6 +1274,31 @@ pub mod windows {
             ui.set_min_width(220.0);
             ui.style_mut().wrap = Some(false);
 
+            ui.horizontal(|ui| {
+                ui.label("Scene:");
+                ui.text_edit_singleline(&mut sandbox_window.scene_path);
+            });
+            ui.horizontal(|ui| {
+                if ui.button("Save").clicked() {
+                    let path = sandbox_window.scene_path.clone();
+                    sandbox_window.scene_status = Some(match sandbox_window.save_scene(&path) {
+                        Ok(()) => format!("Saved {}", path),
+                        Err(error) => format!("Could not save {}: {}", path, error),
+                    });
+                }
+                if ui.button("Open").clicked() {
+                    let path = sandbox_window.scene_path.clone();
+                    sandbox_window.scene_status = Some(match sandbox_window.load_scene(&path) {
+                        Ok(()) => format!("Loaded {}", path),
+                        Err(error) => format!("Could not open {}: {}", path, error),
+                    });
+                }
+            });
+            if let Some(status) = &sandbox_window.scene_status {
+                ui.weak(status);
+            }
+            ui.separator();
+
             #[cfg(not(target_arch = "wasm32"))]
             {
                 egui::gui_zoom::zoom_menu_buttons(ui);
@@ -297,55 +1334,4 @@ pub mod windows {
             }
         });
     }
-
-fn handle_collisions(objects: &mut Vec<Circle>) {
-    let len = objects.len();
-    for i in 0..len {
-        let (left, right) = objects.split_at_mut(i + 1);
-        let obj1 = &mut left[i];
-        for obj2 in right.iter_mut() {
-            let delta = obj1.position - obj2.position;
-            let distance = glm::length(delta);
-            let min_distance = obj1.radius + obj2.radius;
-
-            if distance < min_distance {
-                let normal = glm::normalize(delta);
-                let penetration_depth = min_distance - distance;
-
-                // Resolve the collision by moving the circles apart
-                let total_mass = obj1.mass + obj2.mass;
-                obj1.position = obj1.position + normal * penetration_depth * (obj2.mass / total_mass);
-                obj2.position = obj2.position - normal * penetration_depth * (obj1.mass / total_mass);
-
-                // Calculate the relative velocity
-                let relative_velocity = obj1.velocity - obj2.velocity;
-                let velocity_along_normal = glm::dot(relative_velocity, normal);
-
-                if velocity_along_normal > 0.0 {
-                    continue;
-                }
-
-                // Calculate the impulse scalar
-                let restitution = 10.8; // coefficient of restitution
-                let impulse_scalar = -(1.0 + restitution) * velocity_along_normal;
-                let impulse_scalar = impulse_scalar / (1.0 / obj1.mass + 1.0 / obj2.mass);
-
-                // Apply the impulse to the velocities
-
-                let impulse = glm::vec2(impulse_scalar * normal.x, impulse_scalar * normal.y);
-                obj1.velocity = obj1.velocity + impulse / obj1.mass;
-                obj2.velocity = obj2.velocity - impulse / obj2.mass;
-                if(obj1.foods.contains(&obj2.cell_type)){
-                    obj1.eat(obj2);
-                }
-                if(obj2.foods.contains(&obj1.cell_type)){
-                    obj2.eat(obj1);
-                }
-            }
-        }
-    }
-}
-
-
-
 }
@@ -0,0 +1,1947 @@
+use crate::behavior::{Behavior, BehaviorKind, Behaviors, Neighbourhood, ParticleLife};
+use crate::brain::{self, Activations, Brain};
+use crate::bonds::{Bond, Structure};
+use crate::boundary::{Boundary, BoundaryMode};
+use crate::ccd;
+use crate::chemistry::{Channel, Chemistry};
+use crate::emitters::{Emitter, Sink};
+use crate::genetics::Reproduction;
+use crate::integrator::{integrate, Integrator, TimeStep};
+use crate::interaction::{ForceCurve, InteractionMatrix};
+use crate::lifecycle::{EntityId, LifeStage, Lifecycle};
+use crate::material::Collisions;
+use crate::metabolism::Metabolism;
+use crate::objects::Circle;
+use crate::obstacles::Obstacle;
+use crate::scene::{Scene, SCENE_VERSION};
+use crate::spatial::SpatialHash;
+use crate::species::{Relationship, Species, SpeciesRegistry};
+use crate::timeline::Timeline;
+use ahash::{AHashMap, AHashSet};
+use glm::{dot, length, normalize, vec2, Vec2};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+
+/// How candidate neighbours and collision pairs are found each step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum BroadPhase {
+    /// Compare every cell against every other cell. O(n²), kept as a reference.
+    BruteForce,
+    /// Bucket cells into a uniform grid rebuilt every step.
+    #[default]
+    SpatialHash,
+}
+
+/// Running totals of things that happen during steps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EventCounters {
+    /// Overlapping pairs pushed apart.
+    pub collisions: u64,
+    /// Cells eaten by other cells.
+    pub eaten: u64,
+    /// Cells spawned by emitters.
+    pub emitted: u64,
+    /// Cells taken out by sinks.
+    pub absorbed: u64,
+}
+
+/// Headless particle simulation.
+///
+/// Owns every cell and advances them in `step`. Nothing in here touches SDL,
+/// OpenGL or egui, so a `World` can be driven from plain Rust code and the
+/// sandbox UI is just a layer on top of it.
+///
+/// All randomness is drawn from the world's own seeded RNG, so two worlds
+/// built from the same seed and parameters follow bit-identical trajectories.
+#[derive(Debug, Clone)]
+pub struct World {
+    objects: Vec<Circle>,
+    indices: AHashMap<EntityId, usize>,
+    next_id: u64,
+    pending_removals: Vec<EntityId>,
+    seed: u64,
+    /// Same generator as `rand::rngs::StdRng`, but its state can be saved.
+    rng: ChaCha12Rng,
+    /// Cell types. A cell's `cell_type` indexes into this.
+    pub species: SpeciesRegistry,
+    pub interactions: InteractionMatrix,
+    pub force_curve: ForceCurve,
+    pub metabolism: Metabolism,
+    pub lifecycle: Lifecycle,
+    pub reproduction: Reproduction,
+    pub boundary: Boundary,
+    /// How restitution and friction are picked for cell-cell contacts.
+    pub collisions: Collisions,
+    /// Static geometry. Cells bounce off it with the walls' restitution.
+    pub obstacles: Vec<Obstacle>,
+    /// Springs between cells. Bonds whose cells are gone are dropped.
+    pub bonds: Vec<Bond>,
+    /// Chemical channels cells emit into and follow.
+    pub chemistry: Chemistry,
+    pub emitters: Vec<Emitter>,
+    pub sinks: Vec<Sink>,
+    /// Behaviours species can pick with `BehaviorKind::Custom`. Not part of
+    /// a scene.
+    pub behaviors: Behaviors,
+    pub broad_phase: BroadPhase,
+    pub integrator: Integrator,
+    pub time_step: TimeStep,
+    /// Worker threads for the force phase. 0 uses every available core and
+    /// 1 keeps everything on the calling thread.
+    pub threads: usize,
+    /// Smallest number of circles worth handing to a thread of their own.
+    /// Not part of a scene.
+    pub min_circles_per_thread: usize,
+    /// Snapshots to rewind to. Not part of a scene.
+    pub timeline: Timeline,
+    steps: u64,
+    events: EventCounters,
+    accumulator: f32,
+    neighbour_grid: SpatialHash,
+    collision_grid: SpatialHash,
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self::with_seed(0)
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        let species = SpeciesRegistry::default();
+        Self {
+            objects: Vec::new(),
+            indices: AHashMap::new(),
+            next_id: 1,
+            pending_removals: Vec::new(),
+            seed,
+            rng: ChaCha12Rng::seed_from_u64(seed),
+            interactions: species.interactions(),
+            species,
+            force_curve: ForceCurve::default(),
+            metabolism: Metabolism::default(),
+            lifecycle: Lifecycle::default(),
+            reproduction: Reproduction::default(),
+            boundary: Boundary::default(),
+            collisions: Collisions::default(),
+            obstacles: Vec::new(),
+            bonds: Vec::new(),
+            chemistry: Chemistry::default(),
+            emitters: Vec::new(),
+            sinks: Vec::new(),
+            behaviors: Behaviors::default(),
+            broad_phase: BroadPhase::default(),
+            integrator: Integrator::default(),
+            time_step: TimeStep::default(),
+            threads: 0,
+            min_circles_per_thread: 512,
+            timeline: Timeline::new(),
+            steps: 0,
+            events: EventCounters::default(),
+            accumulator: 0.0,
+            neighbour_grid: SpatialHash::new(),
+            collision_grid: SpatialHash::new(),
+        }
+    }
+
+    /// Feed `elapsed` wall-clock seconds into the fixed-timestep accumulator
+    /// and run as many fixed steps as it holds, up to `time_step.max_catch_up`.
+    /// Returns the number of fixed steps taken.
+    pub fn advance(&mut self, elapsed: f32) -> u32 {
+        let TimeStep { dt, max_catch_up, .. } = self.time_step;
+        if dt <= 0.0 {
+            return 0;
+        }
+
+        self.accumulator += elapsed.max(0.0);
+        let mut steps = 0;
+        while self.accumulator >= dt && steps < max_catch_up {
+            self.fixed_step();
+            self.accumulator -= dt;
+            steps += 1;
+        }
+        if self.accumulator >= dt {
+            // Too far behind to catch up, drop the backlog.
+            self.accumulator %= dt;
+        }
+        steps
+    }
+
+    /// Run a single fixed step of `time_step.dt`, split into its substeps,
+    /// regardless of the accumulator.
+    pub fn fixed_step(&mut self) {
+        let TimeStep { dt, substeps, .. } = self.time_step;
+        let substeps = substeps.max(1);
+        for _ in 0..substeps {
+            self.step(dt / substeps as f32);
+        }
+    }
+
+    /// Advance the simulation by exactly `dt` seconds.
+    pub fn step(&mut self, dt: f32) {
+        // Cells with nobody in range wander instead. Which cells are alone is
+        // decided from the state at the start of the step.
+        let mut isolated = Vec::new();
+        let links: Vec<(usize, usize, Bond)> = self
+            .bonds
+            .iter()
+            .filter_map(|bond| Some((self.index_of(bond.a)?, self.index_of(bond.b)?, *bond)))
+            .collect();
+        let broad_phase = self.broad_phase;
+        let grid = &mut self.neighbour_grid;
+        let boundary = &self.boundary;
+        let species = &self.species;
+        let chemistry = &self.chemistry;
+        let threads = (self.threads, self.min_circles_per_thread);
+        let senses = Senses::new(
+            &self.interactions,
+            &self.force_curve,
+            boundary,
+            species,
+            chemistry,
+            &self.behaviors,
+            self.rng.gen(),
+        );
+        let start: Option<Vec<Vec2>> = if self.collisions.continuous {
+            Some(self.objects.iter().map(|object| object.position).collect())
+        } else {
+            None
+        };
+        integrate(self.integrator, &mut self.objects, dt, |objects| {
+            let forces = steering_forces(objects, broad_phase, grid, &senses, threads);
+            if isolated.is_empty() {
+                isolated = forces.iter().map(Option::is_none).collect();
+            }
+            let mut accelerations: Vec<Vec2> = objects
+                .iter()
+                .zip(forces)
+                .map(|(object, force)| match force {
+                    Some(force) if object.is_alive() => {
+                        force / object.mass - object.velocity * object.friction
+                    }
+                    _ => object.velocity * -object.friction,
+                })
+                .collect();
+            add_bond_forces(&mut accelerations, objects, &links, boundary);
+            if !chemistry.is_empty() {
+                for (acceleration, object) in accelerations.iter_mut().zip(objects) {
+                    if object.is_alive() {
+                        let taxis = chemistry.taxis(object, species, boundary);
+                        *acceleration = *acceleration + taxis / object.mass;
+                    }
+                }
+            }
+            accelerations
+        });
+
+        for (object, isolated) in self.objects.iter_mut().zip(isolated) {
+            object.limit_speed();
+            if isolated && object.is_alive() {
+                object.move_randomly(&mut self.rng);
+            }
+        }
+
+        let mut touching = Vec::new();
+        if let Some(start) = start {
+            self.sweep_collisions(&start, dt, &mut touching);
+        }
+        self.handle_collisions(&mut touching);
+        for object in &mut self.objects {
+            for obstacle in &self.obstacles {
+                obstacle.resolve(object, &self.boundary);
+            }
+            self.boundary.apply(object);
+            if self.boundary.culls(object.position) {
+                object.life = LifeStage::Dead;
+            }
+        }
+        self.form_bonds(&touching);
+        self.break_bonds();
+        self.chemistry.update(&self.objects, &self.species, &self.boundary, dt);
+        self.absorb();
+        self.update_lifecycle(dt);
+        self.emit(dt);
+
+        self.steps += 1;
+        if self.timeline.is_due(self.steps) {
+            let scene = self.to_scene();
+            self.timeline.record(scene);
+        }
+    }
+
+    /// Collisions and eat events since the world was created or loaded.
+    pub fn events(&self) -> EventCounters {
+        self.events
+    }
+
+    /// Number of calls to `step` so far.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Put the world back into the state of timeline snapshot `index`,
+    /// keeping the timeline and registered behaviours. Returns false if there
+    /// is no such snapshot.
+    pub fn rewind(&mut self, index: usize) -> bool {
+        let scene = match self.timeline.get(index) {
+            Some(snapshot) => snapshot.scene().clone(),
+            None => return false,
+        };
+        let timeline = std::mem::take(&mut self.timeline);
+        let behaviors = std::mem::take(&mut self.behaviors);
+        *self = World::from_scene(scene);
+        self.timeline = timeline;
+        self.behaviors = behaviors;
+        true
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Restart the RNG stream from `seed`.
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = ChaCha12Rng::seed_from_u64(seed);
+    }
+
+    /// The world's RNG, for anything that should be reproducible from the seed.
+    pub fn rng(&mut self) -> &mut ChaCha12Rng {
+        &mut self.rng
+    }
+
+    /// Snapshot of the cells, parameters and RNG state. The sandbox-only
+    /// fields `default_object` and `camera` are left at their defaults.
+    pub fn to_scene(&self) -> Scene {
+        Scene {
+            version: SCENE_VERSION,
+            seed: self.seed,
+            rng: Some(self.rng.clone()),
+            next_id: self.next_id,
+            steps: self.steps,
+            events: self.events,
+            species: self.species.clone(),
+            interactions: self.interactions.clone(),
+            force_curve: self.force_curve,
+            metabolism: self.metabolism,
+            lifecycle: self.lifecycle,
+            reproduction: self.reproduction,
+            boundary: self.boundary,
+            collisions: self.collisions,
+            obstacles: self.obstacles.clone(),
+            bonds: self.bonds.clone(),
+            chemistry: self.chemistry.clone(),
+            emitters: self.emitters.clone(),
+            sinks: self.sinks.clone(),
+            broad_phase: self.broad_phase,
+            integrator: self.integrator,
+            time_step: self.time_step,
+            threads: self.threads,
+            objects: self.objects.clone(),
+            default_object: None,
+            camera: Default::default(),
+        }
+    }
+
+    /// Rebuild a world from a scene. It carries on exactly where the world
+    /// the scene was taken from would have.
+    pub fn from_scene(scene: Scene) -> Self {
+        let mut world = Self::with_seed(scene.seed);
+        if let Some(rng) = scene.rng {
+            world.rng = rng;
+        }
+        world.species = scene.species;
+        world.interactions = scene.interactions;
+        world.force_curve = scene.force_curve;
+        world.metabolism = scene.metabolism;
+        world.lifecycle = scene.lifecycle;
+        world.reproduction = scene.reproduction;
+        world.boundary = scene.boundary;
+        world.collisions = scene.collisions;
+        world.obstacles = scene.obstacles;
+        world.bonds = scene.bonds;
+        world.chemistry = scene.chemistry;
+        world.emitters = scene.emitters;
+        world.sinks = scene.sinks;
+        let (columns, rows) = (world.chemistry.columns(), world.chemistry.rows());
+        world.chemistry.set_resolution(columns, rows);
+        world.broad_phase = scene.broad_phase;
+        world.integrator = scene.integrator;
+        world.time_step = scene.time_step;
+        world.threads = scene.threads;
+        world.objects = scene.objects;
+        world.reindex();
+        // Never hand out an id that is already taken
+        let highest = world.objects.iter().map(|object| object.id.0).max().unwrap_or(0);
+        world.next_id = scene.next_id.max(highest + 1);
+        world.steps = scene.steps;
+        world.events = scene.events;
+        world
+    }
+
+    /// Add a cell to the world and return the id it was given. Cells that do
+    /// not belong to a lineage yet become the founder of a new one, and cells
+    /// of a species steered by brains get a random brain if they have none.
+    pub fn spawn(&mut self, mut object: Circle) -> EntityId {
+        let id = EntityId(self.next_id);
+        self.next_id += 1;
+        object.id = id;
+        if object.lineage == EntityId::default() {
+            object.lineage = id;
+        }
+        let behavior = self.species.get(object.cell_type).map(|species| &species.behavior);
+        if let (None, Some(BehaviorKind::Neural(neural))) = (&object.brain, behavior) {
+            object.brain = Some(Brain::random(neural.hidden, &mut self.rng));
+        }
+        self.indices.insert(id, self.objects.len());
+        self.objects.push(object);
+        id
+    }
+
+    /// Queue a cell for removal at the end of the next step. Safe to call
+    /// while holding ids or indices from the current step.
+    pub fn despawn(&mut self, id: EntityId) {
+        self.pending_removals.push(id);
+    }
+
+    /// Remove a cell straight away, returning it if it existed. This shifts
+    /// the index of every later cell; ids stay valid.
+    pub fn remove(&mut self, id: EntityId) -> Option<Circle> {
+        let index = self.index_of(id)?;
+        let object = self.objects.remove(index);
+        self.reindex();
+        Some(object)
+    }
+
+    pub fn clear(&mut self) {
+        self.objects.clear();
+        self.bonds.clear();
+        self.indices.clear();
+        self.pending_removals.clear();
+    }
+
+    /// Replace every cell with `count` random ones drawn from the species
+    /// registry, after restarting the RNG from `seed`. New cells take their
+    /// type, colour, size, mass, speed and diet from their species and
+    /// everything else from `template`.
+    pub fn generate(&mut self, seed: u64, count: usize, template: &Circle) {
+        self.clear();
+        self.reseed(seed);
+        self.apply_species();
+
+        let types = self.species.len() as i32;
+        if types == 0 {
+            return;
+        }
+        for _ in 0..count {
+            let cell_type = self.rng.gen_range(0..types);
+            let position = vec2(
+                self.rng.gen::<f32>() * 0.8 - 0.1,
+                self.rng.gen::<f32>() * 0.8 - 0.1,
+            );
+            if let Some(species) = self.species.get(cell_type) {
+                let object = Circle {
+                    cell_type,
+                    position,
+                    radius: species.radius,
+                    mass: species.mass,
+                    color: species.color,
+                    speed_limit: species.speed_limit,
+                    foods: self.species.diet(cell_type),
+                    ..template.clone()
+                };
+                self.spawn(object);
+            }
+        }
+    }
+
+    /// Give every cell of `cell_type` without a brain a random one with
+    /// `hidden` hidden neurons. Returns how many got one.
+    pub fn grow_brains(&mut self, cell_type: i32, hidden: usize) -> usize {
+        let mut grown = 0;
+        for object in &mut self.objects {
+            if object.cell_type == cell_type && object.brain.is_none() {
+                object.brain = Some(Brain::random(hidden, &mut self.rng));
+                grown += 1;
+            }
+        }
+        grown
+    }
+
+    /// Grow or shrink the interaction matrix to the number of species and
+    /// refresh every cell's diet. Existing coefficients, including ones set
+    /// by hand, are kept; rows and columns new to the matrix start from the
+    /// species relationships.
+    pub fn apply_species(&mut self) {
+        let old_size = self.interactions.size();
+        let size = self.species.len();
+        self.interactions.resize(size);
+        let defaults = self.species.interactions();
+        for from in 0..size {
+            for to in 0..size {
+                if from >= old_size || to >= old_size {
+                    let (from, to) = (from as i32, to as i32);
+                    self.interactions.set(from, to, defaults.get(from, to));
+                }
+            }
+        }
+        for object in &mut self.objects {
+            object.foods = self.species.diet(object.cell_type);
+        }
+    }
+
+    /// Change how `from` treats `to`, updating just that interaction
+    /// coefficient and the diets of `from`'s cells.
+    pub fn set_relationship(&mut self, from: i32, to: i32, relationship: Relationship) {
+        let count = self.species.len();
+        let species = match self.species.get_mut(from) {
+            Some(species) if to >= 0 && (to as usize) < count => species,
+            _ => return,
+        };
+        if species.relationships.len() < count {
+            species.relationships.resize(count, Relationship::Neutral);
+        }
+        species.relationships[to as usize] = relationship;
+        self.interactions.set(from, to, relationship.attraction());
+
+        let diet = self.species.diet(from);
+        for object in self.objects.iter_mut().filter(|object| object.cell_type == from) {
+            object.foods = diet.clone();
+        }
+    }
+
+    /// Remove a chemical channel, along with every species' rules for it.
+    /// Later channels move down one index.
+    pub fn remove_channel(&mut self, channel: usize) -> Option<Channel> {
+        let removed = self.chemistry.remove(channel)?;
+        for species in self.species.iter_mut() {
+            if channel < species.chemicals.len() {
+                species.chemicals.remove(channel);
+            }
+        }
+        Some(removed)
+    }
+
+    /// Remove a species and all of its cells and emitters straight away.
+    /// Every later species, along with its cells and emitters, moves down one
+    /// cell type.
+    pub fn remove_species(&mut self, cell_type: i32) -> Option<Species> {
+        let removed = self.species.remove(cell_type)?;
+        self.interactions.remove(cell_type);
+
+        self.objects.retain(|object| object.cell_type != cell_type);
+        for object in &mut self.objects {
+            if object.cell_type > cell_type {
+                object.cell_type -= 1;
+            }
+            object.foods.retain(|&food| food != cell_type);
+            for food in &mut object.foods {
+                if *food > cell_type {
+                    *food -= 1;
+                }
+            }
+            if (cell_type as usize) < object.genome.attraction.len() {
+                object.genome.attraction.remove(cell_type as usize);
+            }
+        }
+        self.emitters.retain(|emitter| emitter.cell_type != cell_type);
+        for emitter in &mut self.emitters {
+            if emitter.cell_type > cell_type {
+                emitter.cell_type -= 1;
+            }
+        }
+        self.reindex();
+        Some(removed)
+    }
+
+    pub fn index_of(&self, id: EntityId) -> Option<usize> {
+        self.indices.get(&id).copied()
+    }
+
+    /// What cell `id`'s brain senses and does right now, or `None` if it
+    /// has no brain.
+    pub fn think(&self, id: EntityId) -> Option<Activations> {
+        let index = self.index_of(id)?;
+        let brain = self.objects[index].brain.as_ref()?;
+        // Brains don't use the RNG
+        let senses = Senses::new(
+            &self.interactions,
+            &self.force_curve,
+            &self.boundary,
+            &self.species,
+            &self.chemistry,
+            &self.behaviors,
+            [0; 32],
+        );
+        let (mut candidates, mut neighbours) = (Vec::new(), Vec::new());
+        let view = senses.neighbourhood(&self.objects, index, None, &mut candidates, &mut neighbours);
+        Some(brain.think(brain::sense(&view)))
+    }
+
+    pub fn get(&self, id: EntityId) -> Option<&Circle> {
+        self.index_of(id).map(|index| &self.objects[index])
+    }
+
+    pub fn get_mut(&mut self, id: EntityId) -> Option<&mut Circle> {
+        let index = self.index_of(id)?;
+        Some(&mut self.objects[index])
+    }
+
+    pub fn objects(&self) -> &[Circle] {
+        &self.objects
+    }
+
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// Ids of every cell whose centre lies within `radius` of `center`.
+    pub fn query_radius(&self, center: Vec2, radius: f32) -> Vec<EntityId> {
+        self.objects
+            .iter()
+            .filter(|object| length(self.boundary.delta(center, object.position)) <= radius)
+            .map(|object| object.id)
+            .collect()
+    }
+
+    fn reindex(&mut self) {
+        self.indices.clear();
+        for (index, object) in self.objects.iter().enumerate() {
+            self.indices.insert(object.id, index);
+        }
+        let indices = &self.indices;
+        self.bonds
+            .retain(|bond| indices.contains_key(&bond.a) && indices.contains_key(&bond.b));
+    }
+
+    /// Bond two cells at their current distance, with the spring settings of
+    /// `a`'s species. Returns false if either cell is missing, they are the
+    /// same cell or they are already bonded.
+    pub fn bond(&mut self, a: EntityId, b: EntityId) -> bool {
+        let (first, second) = match (self.get(a), self.get(b)) {
+            (Some(first), Some(second)) if a != b => (first, second),
+            _ => return false,
+        };
+        if self.bonds.iter().any(|bond| bond.joins(a, b)) {
+            return false;
+        }
+        let rest_length = length(self.boundary.delta(first.position, second.position));
+        let bonding = self
+            .species
+            .get(first.cell_type)
+            .map(|species| species.bonding)
+            .unwrap_or_default();
+        self.bonds.push(bonding.bond(a, b, rest_length));
+        true
+    }
+
+    /// Remove the bond between two cells, returning false if there was none.
+    pub fn unbond(&mut self, a: EntityId, b: EntityId) -> bool {
+        let before = self.bonds.len();
+        self.bonds.retain(|bond| !bond.joins(a, b));
+        self.bonds.len() != before
+    }
+
+    /// Drop a bonded `structure` of `cell_type` cells centred on `center`,
+    /// with neighbouring cells just touching, and return the new cells' ids.
+    /// Nothing is added for an unknown cell type.
+    pub fn spawn_structure(
+        &mut self,
+        cell_type: i32,
+        center: Vec2,
+        structure: Structure,
+    ) -> Vec<EntityId> {
+        let (radius, bonding) = match self.species.get(cell_type) {
+            Some(species) => (species.radius, species.bonding),
+            None => return Vec::new(),
+        };
+        let (offsets, links) = structure.layout(radius * 2.0);
+        let mut ids = Vec::with_capacity(offsets.len());
+        for offset in &offsets {
+            if let Some(object) = self.species.create(cell_type, center + *offset) {
+                ids.push(self.spawn(object));
+            }
+        }
+        for (i, j) in links {
+            let rest_length = length(offsets[j] - offsets[i]);
+            self.bonds.push(bonding.bond(ids[i], ids[j], rest_length));
+        }
+        ids
+    }
+
+    /// Mark every cell inside a sink for removal at the end of the step.
+    fn absorb(&mut self) {
+        if self.sinks.is_empty() {
+            return;
+        }
+        let boundary = &self.boundary;
+        for object in &mut self.objects {
+            if object.life != LifeStage::Dead
+                && self.sinks.iter().any(|sink| sink.contains(object.position, boundary))
+            {
+                object.life = LifeStage::Dead;
+                self.events.absorbed += 1;
+            }
+        }
+    }
+
+    /// Spawn whatever the emitters owe for the last `dt` seconds.
+    fn emit(&mut self, dt: f32) {
+        for index in 0..self.emitters.len() {
+            let due = self.emitters[index].due(dt);
+            if due == 0 {
+                continue;
+            }
+            let emitter = self.emitters[index].clone();
+            let mut nearby = if emitter.limit > 0 {
+                self.objects
+                    .iter()
+                    .filter(|object| {
+                        object.cell_type == emitter.cell_type
+                            && object.life != LifeStage::Dead
+                            && length(self.boundary.delta(emitter.position, object.position)) <= emitter.spread
+                    })
+                    .count()
+            } else {
+                0
+            };
+
+            for _ in 0..due {
+                let crowded = emitter.limit > 0 && nearby >= emitter.limit;
+                if crowded || self.objects.len() >= self.reproduction.max_population {
+                    self.emitters[index].pause();
+                    break;
+                }
+                let (position, velocity) = emitter.sample(&mut self.rng);
+                if let Some(mut object) = self.species.create(emitter.cell_type, position) {
+                    object.velocity = velocity;
+                    self.spawn(object);
+                    self.events.emitted += 1;
+                    nearby += 1;
+                }
+            }
+        }
+    }
+
+    /// Bond living cells that touched this step where their species say so.
+    fn form_bonds(&mut self, touching: &[(usize, usize)]) {
+        if touching.is_empty() || !self.species.iter().any(|species| species.bonding.automatic) {
+            return;
+        }
+
+        let mut counts: AHashMap<EntityId, u32> = AHashMap::new();
+        let mut bonded = AHashSet::new();
+        for bond in &self.bonds {
+            *counts.entry(bond.a).or_default() += 1;
+            *counts.entry(bond.b).or_default() += 1;
+            bonded.insert((bond.a.min(bond.b), bond.a.max(bond.b)));
+        }
+
+        for &(i, j) in touching {
+            let (first, second) = (&self.objects[i], &self.objects[j]);
+            if !first.is_alive() || !second.is_alive() {
+                continue;
+            }
+            let bonding = match self.species.contact_bonding(first.cell_type, second.cell_type) {
+                Some(bonding) => bonding,
+                None => continue,
+            };
+            let full = |id| counts.get(&id).copied().unwrap_or(0) >= bonding.max_bonds;
+            let pair = (first.id.min(second.id), first.id.max(second.id));
+            if full(first.id) || full(second.id) || !bonded.insert(pair) {
+                continue;
+            }
+            self.bonds.push(bonding.bond(first.id, second.id, first.radius + second.radius));
+            *counts.entry(first.id).or_default() += 1;
+            *counts.entry(second.id).or_default() += 1;
+        }
+    }
+
+    /// Snap every bond stretched or squashed past its break force.
+    fn break_bonds(&mut self) {
+        let objects = &self.objects;
+        let indices = &self.indices;
+        let boundary = &self.boundary;
+        self.bonds.retain(|bond| match (indices.get(&bond.a), indices.get(&bond.b)) {
+            (Some(&i), Some(&j)) => {
+                let distance = length(boundary.delta(objects[i].position, objects[j].position));
+                !bond.breaks(distance)
+            }
+            _ => false,
+        });
+    }
+
+    /// Burn energy, turn starved cells into corpses, rot corpses away, drop
+    /// everything that died during the step and finally split well-fed cells.
+    fn update_lifecycle(&mut self, dt: f32) {
+        for id in std::mem::take(&mut self.pending_removals) {
+            if let Some(index) = self.index_of(id) {
+                self.objects[index].life = LifeStage::Dead;
+            }
+        }
+
+        for object in &mut self.objects {
+            match object.life {
+                LifeStage::Alive => {
+                    object.energy -= self.metabolism.drain(object, dt);
+                    if self.metabolism.enabled && object.energy <= 0.0 {
+                        object.energy = 0.0;
+                        object.life = if self.lifecycle.leave_corpses {
+                            LifeStage::Corpse {
+                                remaining: self.lifecycle.corpse_lifetime,
+                            }
+                        } else {
+                            LifeStage::Dead
+                        };
+                    }
+                }
+                LifeStage::Corpse { remaining } => {
+                    let remaining = remaining - dt;
+                    object.life = if remaining > 0.0 {
+                        LifeStage::Corpse { remaining }
+                    } else {
+                        LifeStage::Dead
+                    };
+                }
+                LifeStage::Dead => {}
+            }
+        }
+
+        let before = self.objects.len();
+        self.objects.retain(|object| object.life != LifeStage::Dead);
+        if self.objects.len() != before {
+            self.reindex();
+        }
+
+        for child in self.reproduce() {
+            self.spawn(child);
+        }
+    }
+
+    /// Split every living cell that has enough energy, returning the offspring.
+    fn reproduce(&mut self) -> Vec<Circle> {
+        let mut births = Vec::new();
+        if !self.reproduction.enabled {
+            return births;
+        }
+
+        let room = self.reproduction.max_population.saturating_sub(self.objects.len());
+        let types = self.interactions.size();
+        for parent in &mut self.objects {
+            if births.len() >= room {
+                break;
+            }
+            if !parent.is_alive() || parent.energy < self.reproduction.energy_threshold {
+                continue;
+            }
+
+            parent.energy /= 2.0;
+            let mut child = parent.clone();
+            child.parent = Some(parent.id);
+            child.generation = parent.generation + 1;
+            self.reproduction.mutate(&mut child, types, &mut self.rng);
+
+            // Place the two halves side by side, touching
+            let angle = self.rng.gen_range(0.0..std::f32::consts::PI * 2.0);
+            let offset = vec2(angle.cos(), angle.sin()) * ((parent.radius + child.radius) * 0.5);
+            child.position = parent.position + offset;
+            parent.position = parent.position - offset;
+            births.push(child);
+        }
+        births
+    }
+
+    fn handle_collisions(&mut self, touching: &mut Vec<(usize, usize)>) {
+        let rules = ContactRules {
+            metabolism: &self.metabolism,
+            boundary: &self.boundary,
+            species: &self.species,
+            collisions: &self.collisions,
+        };
+        match self.broad_phase {
+            BroadPhase::BruteForce => {
+                for i in 0..self.objects.len() {
+                    for j in i + 1..self.objects.len() {
+                        resolve_collision(&mut self.objects, i, j, &rules, &mut self.events, touching);
+                    }
+                }
+            }
+            BroadPhase::SpatialHash => {
+                // Two cells can only overlap if they sit in neighbouring grid
+                // cells when the grid is at least one diameter wide. The grid
+                // is built once, so it gets one more radius of room for
+                // pushes that close a gap during the pass. Candidates come
+                // back in brute-force order and every pair rechecks its
+                // distance, so the result matches the brute-force scan
+                // unless pushes move a pair together by more than that.
+                let max_radius = self.objects.iter().map(|object| object.radius).fold(0.0, f32::max);
+                rebuild_grid(
+                    &mut self.collision_grid,
+                    3.0 * max_radius,
+                    &self.boundary,
+                    &self.objects,
+                );
+                for (i, j) in self.collision_grid.pairs() {
+                    resolve_collision(&mut self.objects, i, j, &rules, &mut self.events, touching);
+                }
+            }
+        }
+    }
+
+    /// Walk every cell from `start` to where the integrator put it, stopping
+    /// the world at each contact along the way in time order and bouncing the
+    /// cells involved. Cells that hit nothing end up exactly where the
+    /// integrator put them.
+    fn sweep_collisions(&mut self, start: &[Vec2], dt: f32, touching: &mut Vec<(usize, usize)>) {
+        // Movement over the whole step, changed by impulses as they happen
+        let mut motion: Vec<Vec2> = self
+            .objects
+            .iter()
+            .zip(start)
+            .map(|(object, start)| object.position - *start)
+            .collect();
+        let end: Vec<Vec2> = self.objects.iter().map(|object| object.position).collect();
+        for (object, start) in self.objects.iter_mut().zip(start) {
+            object.position = *start;
+        }
+        let pairs = self.swept_pairs(&motion);
+        let mut hit = vec![false; motion.len()];
+
+        let rules = ContactRules {
+            metabolism: &self.metabolism,
+            boundary: &self.boundary,
+            species: &self.species,
+            collisions: &self.collisions,
+        };
+        let boundary = &self.boundary;
+        let obstacles = &self.obstacles;
+        let objects = &mut self.objects;
+        let events = &mut self.events;
+
+        // Impacts are stored as absolute times within the step, so only the
+        // cells that change course need their impacts looked up again.
+        let static_impact = |object: &Circle, motion: Vec2, time: f32| {
+            if object.life == LifeStage::Dead {
+                return None;
+            }
+            let motion = motion * (1.0 - time);
+            let wall = match boundary.mode {
+                BoundaryMode::Walls => ccd::walls(boundary, object.position, object.radius, motion),
+                _ => None,
+            };
+            obstacles
+                .iter()
+                .filter_map(|obstacle| {
+                    let position = obstacle.nearest_image(object.position, boundary);
+                    ccd::obstacle(obstacle, position, object.radius, motion)
+                })
+                .chain(wall)
+                .fold(None, ccd::earliest)
+                .map(|(t, normal)| (time + t * (1.0 - time), normal))
+        };
+        let pair_impact = |objects: &[Circle], motion: &[Vec2], (i, j): (usize, usize), time: f32| {
+            let (obj1, obj2) = (&objects[i], &objects[j]);
+            if obj1.life == LifeStage::Dead || obj2.life == LifeStage::Dead {
+                return None;
+            }
+            let delta = boundary.delta(obj2.position, obj1.position);
+            let relative_motion = (motion[i] - motion[j]) * (1.0 - time);
+            ccd::circles(delta, relative_motion, obj1.radius + obj2.radius)
+                .map(|t| time + t * (1.0 - time))
+        };
+
+        let mut time = 0.0;
+        let mut static_impacts: Vec<Option<(f32, Vec2)>> = objects
+            .iter()
+            .zip(&motion)
+            .map(|(object, motion)| static_impact(object, *motion, time))
+            .collect();
+        let mut pair_impacts: Vec<Option<f32>> =
+            pairs.iter().map(|&pair| pair_impact(objects, &motion, pair, time)).collect();
+
+        for _ in 0..self.collisions.max_impacts {
+            let next = static_impacts
+                .iter()
+                .enumerate()
+                .filter_map(|(i, impact)| impact.map(|(t, normal)| (t, Impact::Static(i, normal))))
+                .chain(
+                    pair_impacts
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(k, impact)| impact.map(|t| (t, Impact::Pair(k)))),
+                )
+                .fold(None, ccd::earliest);
+            let (impact_time, impact) = match next {
+                Some(next) => next,
+                None => break,
+            };
+
+            for (object, motion) in objects.iter_mut().zip(&motion) {
+                object.position = object.position + *motion * (impact_time - time);
+            }
+            time = impact_time;
+
+            let (i, j) = match impact {
+                Impact::Static(i, normal) => {
+                    let object = &mut objects[i];
+                    object.velocity = ccd::reflect(object.velocity, normal, boundary.restitution);
+                    motion[i] = ccd::reflect(motion[i], normal, boundary.restitution);
+                    (i, i)
+                }
+                Impact::Pair(k) => {
+                    // The paths are what met, so bounce those and carry the
+                    // same change over to the velocities.
+                    let (i, j) = pairs[k];
+                    let velocities = (objects[i].velocity, objects[j].velocity);
+                    objects[i].velocity = motion[i] / dt;
+                    objects[j].velocity = motion[j] / dt;
+                    let normal = normalize(boundary.delta(objects[j].position, objects[i].position));
+                    events.collisions += 1;
+                    touching.push((i, j));
+                    let (left, right) = objects.split_at_mut(j);
+                    apply_contact(&mut left[i], &mut right[0], normal, &rules, events);
+
+                    let bounced = (objects[i].velocity * dt, objects[j].velocity * dt);
+                    objects[i].velocity = velocities.0 + (bounced.0 - motion[i]) / dt;
+                    objects[j].velocity = velocities.1 + (bounced.1 - motion[j]) / dt;
+                    motion[i] = bounced.0;
+                    motion[j] = bounced.1;
+                    (i, j)
+                }
+            };
+
+            // Only the cells that were hit changed course
+            hit[i] = true;
+            hit[j] = true;
+            for k in [i, j] {
+                static_impacts[k] = static_impact(&objects[k], motion[k], time);
+            }
+            for (pair, impact) in pairs.iter().zip(pair_impacts.iter_mut()) {
+                if pair.0 == i || pair.0 == j || pair.1 == i || pair.1 == j {
+                    *impact = pair_impact(objects, &motion, *pair, time);
+                }
+            }
+        }
+
+        for (k, object) in objects.iter_mut().enumerate() {
+            object.position = if hit[k] {
+                object.position + motion[k] * (1.0 - time)
+            } else {
+                // Exactly, not just up to rounding
+                end[k]
+            };
+        }
+    }
+
+    /// Pairs whose paths over the step could cross, found by bounding each
+    /// path with a circle. A cell knocked off its path can still meet one not
+    /// in here; the overlap pass catches those.
+    fn swept_pairs(&mut self, motion: &[Vec2]) -> Vec<(usize, usize)> {
+        match self.broad_phase {
+            BroadPhase::BruteForce => {
+                let count = self.objects.len();
+                (0..count).flat_map(|i| (i + 1..count).map(move |j| (i, j))).collect()
+            }
+            BroadPhase::SpatialHash => {
+                let bounds: Vec<(Vec2, f32)> = self
+                    .objects
+                    .iter()
+                    .zip(motion)
+                    .map(|(object, motion)| {
+                        (object.position + *motion * 0.5, object.radius + length(*motion) * 0.5)
+                    })
+                    .collect();
+                let max_radius = bounds.iter().map(|bound| bound.1).fold(0.0, f32::max);
+                let positions = bounds.iter().map(|bound| bound.0);
+                if self.boundary.mode == BoundaryMode::Wrap {
+                    let (min, size) = (self.boundary.min, self.boundary.size());
+                    self.collision_grid.rebuild_wrapped(2.0 * max_radius, min, size, positions);
+                } else {
+                    self.collision_grid.rebuild(2.0 * max_radius, positions);
+                }
+                self.collision_grid.pairs()
+            }
+        }
+    }
+}
+
+/// Next contact found by the sweep.
+#[derive(Debug, Clone, Copy)]
+enum Impact {
+    /// A cell hits a wall or an obstacle with this outward normal.
+    Static(usize, Vec2),
+    /// Index into the candidate pairs.
+    Pair(usize),
+}
+
+/// Read-only world settings behaviours see, plus what each species steers
+/// with.
+struct Senses<'a> {
+    interactions: &'a InteractionMatrix,
+    curve: &'a ForceCurve,
+    boundary: &'a Boundary,
+    species: &'a SpeciesRegistry,
+    chemistry: &'a Chemistry,
+    /// Indexed by cell type. Cells of unknown types use particle life.
+    behaviors: Vec<&'a dyn Behavior>,
+    /// Drawn from the world's RNG once per step. Each cell gets its own
+    /// stream of it.
+    seed: [u8; 32],
+}
+
+impl<'a> Senses<'a> {
+    fn new(
+        interactions: &'a InteractionMatrix,
+        curve: &'a ForceCurve,
+        boundary: &'a Boundary,
+        species: &'a SpeciesRegistry,
+        chemistry: &'a Chemistry,
+        behaviors: &'a Behaviors,
+        seed: [u8; 32],
+    ) -> Self {
+        Self {
+            interactions,
+            curve,
+            boundary,
+            species,
+            chemistry,
+            behaviors: species
+                .iter()
+                .map(|species| species.behavior.resolve(behaviors))
+                .collect(),
+            seed,
+        }
+    }
+
+    fn behavior(&self, cell_type: i32) -> &'a dyn Behavior {
+        let behavior = if cell_type >= 0 {
+            self.behaviors.get(cell_type as usize).copied()
+        } else {
+            None
+        };
+        behavior.unwrap_or(&ParticleLife)
+    }
+
+    /// How far cells of `cell_type` see: the force curve's cutoff, or
+    /// further if their behaviour asks for it.
+    fn radius(&self, cell_type: i32) -> f32 {
+        self.behavior(cell_type).perception().max(self.curve.cutoff_radius)
+    }
+
+    /// The view `objects[i]` steers by. `neighbours` is filled with every
+    /// other circle within its radius, in storage order, found through
+    /// `grid` if there is one. The grid's cells must be at least that radius.
+    fn neighbourhood<'b, 'c: 'b>(
+        &'b self,
+        objects: &'c [Circle],
+        i: usize,
+        grid: Option<&SpatialHash>,
+        candidates: &mut Vec<usize>,
+        neighbours: &'b mut Vec<&'c Circle>,
+    ) -> Neighbourhood<'b> {
+        let object = &objects[i];
+        let radius = self.radius(object.cell_type);
+        let boundary = self.boundary;
+        let within = |other: &&Circle| length(boundary.delta(object.position, other.position)) <= radius;
+        neighbours.clear();
+        match grid {
+            None => {
+                let others = objects
+                    .iter()
+                    .enumerate()
+                    .filter(|&(j, _)| j != i)
+                    .map(|(_, other)| other);
+                neighbours.extend(others.filter(within));
+            }
+            Some(grid) => {
+                candidates.clear();
+                grid.query(object.position, radius, candidates);
+                let others = candidates
+                    .iter()
+                    .filter(|&&j| j != i)
+                    .map(|&j| &objects[j]);
+                neighbours.extend(others.filter(within));
+            }
+        }
+
+        Neighbourhood {
+            cell: object,
+            neighbours,
+            radius,
+            boundary,
+            species: self.species,
+            interactions: self.interactions,
+            force_curve: self.curve,
+            chemistry: self.chemistry,
+        }
+    }
+}
+
+/// Steering force every living circle's behaviour picks, `None` for circles
+/// with nothing to react to.
+///
+/// `objects` is the front buffer and is only read; each worker writes its
+/// own slice of the returned back buffer. Every circle sees the same state
+/// and its neighbours in the same order, so the result is identical for any
+/// number of threads. `threads` is the thread count and the smallest chunk
+/// of circles worth a thread, see `World::threads`.
+fn steering_forces<'a>(
+    objects: &'a [Circle],
+    broad_phase: BroadPhase,
+    grid: &mut SpatialHash,
+    senses: &Senses,
+    (threads, min_chunk): (usize, usize),
+) -> Vec<Option<Vec2>> {
+    let grid = match broad_phase {
+        BroadPhase::SpatialHash => {
+            // Sized for the species that sees furthest
+            let radius = senses
+                .behaviors
+                .iter()
+                .map(|behavior| behavior.perception())
+                .fold(senses.curve.cutoff_radius, f32::max);
+            rebuild_grid(grid, radius, senses.boundary, objects);
+            Some(&*grid)
+        }
+        BroadPhase::BruteForce => None,
+    };
+
+    let force_on = |i: usize, candidates: &mut Vec<usize>, neighbours: &mut Vec<&'a Circle>| {
+        let object = &objects[i];
+        if !object.is_alive() {
+            return None;
+        }
+        let view = senses.neighbourhood(objects, i, grid, candidates, neighbours);
+        let mut rng = ChaCha12Rng::from_seed(senses.seed);
+        rng.set_stream(object.id.0);
+        senses.behavior(object.cell_type).steer(&view, &mut rng)
+    };
+
+    let mut forces = vec![None; objects.len()];
+    let threads = if threads == 0 {
+        std::thread::available_parallelism().map_or(1, |n| n.get())
+    } else {
+        threads
+    };
+    let chunk = objects
+        .len()
+        .div_ceil(threads)
+        .max(min_chunk.max(1));
+
+    if chunk >= objects.len() {
+        let (mut candidates, mut neighbours) = (Vec::new(), Vec::new());
+        for (i, force) in forces.iter_mut().enumerate() {
+            *force = force_on(i, &mut candidates, &mut neighbours);
+        }
+    } else {
+        let force_on = &force_on;
+        std::thread::scope(|scope| {
+            for (c, back) in forces.chunks_mut(chunk).enumerate() {
+                scope.spawn(move || {
+                    let (mut candidates, mut neighbours) = (Vec::new(), Vec::new());
+                    for (k, force) in back.iter_mut().enumerate() {
+                        *force = force_on(c * chunk + k, &mut candidates, &mut neighbours);
+                    }
+                });
+            }
+        });
+    }
+    forces
+}
+
+/// Add the pull of every bonded pair `(i, j, bond)` to `accelerations`.
+fn add_bond_forces(
+    accelerations: &mut [Vec2],
+    objects: &[Circle],
+    links: &[(usize, usize, Bond)],
+    boundary: &Boundary,
+) {
+    for &(i, j, bond) in links {
+        let (a, b) = (&objects[i], &objects[j]);
+        let force = bond.force(boundary.delta(a.position, b.position), b.velocity - a.velocity);
+        accelerations[i] = accelerations[i] + force / a.mass;
+        accelerations[j] = accelerations[j] - force / b.mass;
+    }
+}
+
+/// Bucket `objects` into `grid`, wrapping across the seams in wrap mode so
+/// neighbours on opposite edges end up in neighbouring cells.
+fn rebuild_grid(grid: &mut SpatialHash, cell_size: f32, boundary: &Boundary, objects: &[Circle]) {
+    let positions = objects.iter().map(|object| object.position);
+    if boundary.mode == BoundaryMode::Wrap {
+        grid.rebuild_wrapped(cell_size, boundary.min, boundary.size(), positions);
+    } else {
+        grid.rebuild(cell_size, positions);
+    }
+}
+
+/// Read-only world settings needed to resolve a contact.
+struct ContactRules<'a> {
+    metabolism: &'a Metabolism,
+    boundary: &'a Boundary,
+    species: &'a SpeciesRegistry,
+    collisions: &'a Collisions,
+}
+
+fn resolve_collision(
+    objects: &mut [Circle],
+    i: usize,
+    j: usize,
+    rules: &ContactRules,
+    events: &mut EventCounters,
+    touching: &mut Vec<(usize, usize)>,
+) {
+    let (left, right) = objects.split_at_mut(j);
+    let obj1 = &mut left[i];
+    let obj2 = &mut right[0];
+    if obj1.life == LifeStage::Dead || obj2.life == LifeStage::Dead {
+        // Already eaten this step
+        return;
+    }
+
+    let delta = rules.boundary.delta(obj2.position, obj1.position);
+    let distance = length(delta);
+    let min_distance = obj1.radius + obj2.radius;
+
+    if distance < min_distance {
+        let normal = normalize(delta);
+        let penetration_depth = min_distance - distance;
+        events.collisions += 1;
+        touching.push((i, j));
+
+        // Resolve the collision by moving the circles apart
+        let total_mass = obj1.mass + obj2.mass;
+        obj1.position = obj1.position + normal * penetration_depth * (obj2.mass / total_mass);
+        obj2.position = obj2.position - normal * penetration_depth * (obj1.mass / total_mass);
+
+        apply_contact(obj1, obj2, normal, rules, events);
+    }
+}
+
+/// Bounce two touching cells off each other along `normal`, which points from
+/// `obj2` to `obj1`, and let one eat the other.
+fn apply_contact(
+    obj1: &mut Circle,
+    obj2: &mut Circle,
+    normal: Vec2,
+    rules: &ContactRules,
+    events: &mut EventCounters,
+) {
+    // Calculate the relative velocity
+    let relative_velocity = obj1.velocity - obj2.velocity;
+    let velocity_along_normal = dot(relative_velocity, normal);
+
+    if velocity_along_normal > 0.0 {
+        return;
+    }
+
+    let contact = rules.collisions.contact(
+        &rules.species.material(obj1.cell_type),
+        &rules.species.material(obj2.cell_type),
+    );
+    let inverse_mass = 1.0 / obj1.mass + 1.0 / obj2.mass;
+
+    // Calculate the impulse scalar
+    let impulse_scalar = -(1.0 + contact.restitution) * velocity_along_normal;
+    let impulse_scalar = impulse_scalar / inverse_mass;
+
+    // Apply the impulse to the velocities
+    let impulse = vec2(impulse_scalar * normal.x, impulse_scalar * normal.y);
+    obj1.velocity = obj1.velocity + impulse / obj1.mass;
+    obj2.velocity = obj2.velocity - impulse / obj2.mass;
+
+    // Surface friction opposes sliding, up to the Coulomb limit
+    let relative_velocity = obj1.velocity - obj2.velocity;
+    let tangent = vec2(-normal.y, normal.x);
+    let sliding = dot(relative_velocity, tangent);
+    let limit = contact.friction * impulse_scalar;
+    let friction_scalar = (-sliding / inverse_mass).clamp(-limit, limit);
+    let friction_impulse = tangent * friction_scalar;
+    obj1.velocity = obj1.velocity + friction_impulse / obj1.mass;
+    obj2.velocity = obj2.velocity - friction_impulse / obj2.mass;
+    if obj1.can_eat(obj2) {
+        obj1.eat(obj2, rules.metabolism);
+        events.eaten += 1;
+    } else if obj2.can_eat(obj1) {
+        obj2.eat(obj1, rules.metabolism);
+        events.eaten += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::behavior::{BehaviorKind, Boids};
+    use crate::brain::Neural;
+    use crate::emitters::MAX_EMITTED_PER_STEP;
+    use glm::vec3;
+    use rand::{rngs::StdRng, Rng, RngCore};
+
+    fn cell(x: f32, y: f32) -> Circle {
+        Circle::new(1, vec2(x, y), 0.01, vec3(1.0, 1.0, 1.0), 1.0)
+    }
+
+    #[test]
+    fn spawn_query_and_remove() {
+        let mut world = World::new();
+        let near = world.spawn(cell(0.0, 0.0));
+        let far = world.spawn(cell(0.5, 0.0));
+        assert_eq!(world.len(), 2);
+        assert_eq!(world.query_radius(vec2(0.1, 0.0), 0.2), vec![near]);
+
+        let removed = world.remove(near).unwrap();
+        assert_eq!(removed.position, vec2(0.0, 0.0));
+        assert_eq!(world.len(), 1);
+        assert_eq!(world.get(far).unwrap().position, vec2(0.5, 0.0));
+        assert!(world.remove(near).is_none());
+    }
+
+    #[test]
+    fn wrapped_radius_queries_see_across_the_seam() {
+        let mut world = World::new();
+        world.boundary.mode = BoundaryMode::Wrap;
+        let across = world.spawn(cell(-0.95, 0.0));
+        world.spawn(cell(0.5, 0.0));
+        assert_eq!(world.query_radius(vec2(0.95, 0.0), 0.2), vec![across]);
+
+        world.boundary.mode = BoundaryMode::Walls;
+        assert!(world.query_radius(vec2(0.95, 0.0), 0.2).is_empty());
+    }
+
+    #[test]
+    fn step_moves_cells_and_pushes_overlaps_apart() {
+        let mut world = World::new();
+        let mut moving = cell(-0.5, 0.0);
+        moving.velocity = vec2(0.1, 0.0);
+        world.spawn(moving);
+        world.spawn(cell(0.5, 0.0));
+        world.spawn(cell(0.505, 0.0));
+
+        world.step(0.1);
+        let objects = world.objects();
+        // Friction takes 5% off before the cell moves
+        assert!((objects[0].position.x - (-0.5 + 0.1 * 0.95 * 0.1)).abs() < 1e-6);
+        let gap = length(objects[2].position - objects[1].position);
+        assert!(gap >= 0.02 - 1e-6, "cells still overlap: {}", gap);
+    }
+
+    /// `count` cells of four types packed into a box narrower than the
+    /// cutoff radius, so every cell reacts to the others instead of
+    /// wandering off at random.
+    fn crowded(seed: u64, count: usize) -> World {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut world = World::new();
+        for from in 0..4 {
+            for to in 0..4 {
+                world.interactions.set(from, to, rng.gen_range(-1.0..1.0));
+            }
+        }
+        for _ in 0..count {
+            let position = vec2(rng.gen::<f32>() * 0.3, rng.gen::<f32>() * 0.3);
+            let radius = rng.gen_range(0.005..0.015);
+            world.spawn(Circle::new(rng.gen_range(0..4), position, radius, vec3(1.0, 1.0, 1.0), 0.1));
+        }
+        world
+    }
+
+    /// Step both worlds side by side, failing on the first step they differ.
+    fn assert_same_steps(a: &mut World, b: &mut World, steps: usize) {
+        for step in 0..steps {
+            a.step(0.01);
+            b.step(0.01);
+            assert!(a.objects() == b.objects(), "cells differ after step {}", step);
+        }
+    }
+
+    #[test]
+    fn broad_phases_agree() {
+        for seed in 1..=3 {
+            for mode in [BoundaryMode::Walls, BoundaryMode::Wrap, BoundaryMode::Open] {
+                let mut grid = crowded(seed, 200);
+                grid.boundary.mode = mode;
+                if mode == BoundaryMode::Wrap {
+                    // The edge of the crowd sits across both seams
+                    grid.boundary.min = vec2(0.05, 0.05);
+                    grid.boundary.max = vec2(1.05, 1.05);
+                }
+                grid.broad_phase = BroadPhase::SpatialHash;
+                let mut brute = grid.clone();
+                brute.broad_phase = BroadPhase::BruteForce;
+                assert_same_steps(&mut grid, &mut brute, 10);
+            }
+        }
+    }
+
+    /// `count` cells spread thinly enough that some wander at random.
+    fn scattered(seed: u64, count: usize) -> World {
+        let mut world = World::with_seed(seed);
+        for _ in 0..count {
+            let rng = world.rng();
+            let position = vec2(rng.gen::<f32>() * 10.0, rng.gen::<f32>() * 10.0);
+            let cell_type = rng.gen_range(0..4);
+            world.spawn(Circle::new(cell_type, position, 0.01, vec3(1.0, 1.0, 1.0), 0.1));
+        }
+        world
+    }
+
+    #[test]
+    fn same_seed_steps_identically() {
+        let bits = |world: &World| -> Vec<[u32; 4]> {
+            world
+                .objects()
+                .iter()
+                .map(|object| {
+                    let (p, v) = (object.position, object.velocity);
+                    [p.x.to_bits(), p.y.to_bits(), v.x.to_bits(), v.y.to_bits()]
+                })
+                .collect()
+        };
+        let mut a = scattered(7, 100);
+        let mut b = scattered(7, 100);
+        let mut other = scattered(7, 100);
+        other.reseed(8);
+        for _ in 0..20 {
+            a.step(0.01);
+            b.step(0.01);
+            other.step(0.01);
+        }
+        assert_eq!(bits(&a), bits(&b));
+        assert_ne!(bits(&a), bits(&other));
+    }
+
+    #[test]
+    fn advance_runs_whole_steps_and_caps_catch_up() {
+        let mut world = World::new();
+        world.time_step = TimeStep {
+            dt: 0.01,
+            substeps: 2,
+            max_catch_up: 5,
+        };
+        assert_eq!(world.advance(0.025), 2);
+        // The left-over half step carries over to the next call
+        assert_eq!(world.advance(0.005), 1);
+        // A long stall runs at most max_catch_up steps and drops the rest
+        assert_eq!(world.advance(1.0), 5);
+        assert_eq!(world.advance(0.0), 0);
+    }
+
+    #[test]
+    fn thread_count_does_not_change_results() {
+        let integrators = [Integrator::SemiImplicitEuler, Integrator::VelocityVerlet, Integrator::Rk4];
+        for integrator in integrators {
+            let mut single = crowded(11, 200);
+            single.integrator = integrator;
+            single.threads = 1;
+            let mut many = single.clone();
+            many.threads = 4;
+            // Small enough for 200 cells to be split across the threads
+            many.min_circles_per_thread = 16;
+            assert_same_steps(&mut single, &mut many, 10);
+        }
+
+        // The default chunk size splits only worlds with well over 512 cells
+        let mut single = crowded(12, 1200);
+        single.threads = 1;
+        let mut many = single.clone();
+        many.threads = 2;
+        assert_same_steps(&mut single, &mut many, 3);
+    }
+
+    #[test]
+    fn starving_cells_leave_corpses_that_rot_away() {
+        let mut world = World::new();
+        world.lifecycle.corpse_lifetime = 0.025;
+        let mut starving = cell(-0.5, 0.0);
+        starving.energy = 0.005;
+        let starving = world.spawn(starving);
+
+        world.step(0.01);
+        let corpse = world.get(starving).unwrap();
+        assert_eq!(corpse.energy, 0.0);
+        assert_eq!(corpse.life, LifeStage::Corpse { remaining: 0.025 });
+        world.step(0.01);
+        world.step(0.01);
+        assert!(world.get(starving).is_some());
+        world.step(0.01);
+        assert!(world.get(starving).is_none());
+        assert!(world.is_empty());
+    }
+
+    #[test]
+    fn eaten_and_despawned_cells_go_at_the_end_of_the_step() {
+        let mut world = World::new();
+        let first = world.spawn(cell(-0.5, 0.0));
+        let mut hunter = cell(0.5, 0.0);
+        hunter.foods = vec![2];
+        hunter.velocity = vec2(0.1, 0.0);
+        let hunter = world.spawn(hunter);
+        let mut prey = Circle::new(2, vec2(0.515, 0.0), 0.01, vec3(1.0, 1.0, 1.0), 1.0);
+        prey.velocity = vec2(-0.1, 0.0);
+        let prey = world.spawn(prey);
+        let last = world.spawn(cell(0.0, 0.5));
+
+        world.despawn(first);
+        assert!(world.get(first).is_some());
+        world.step(0.01);
+
+        assert_eq!(world.len(), 2);
+        assert!(world.get(first).is_none());
+        assert!(world.get(prey).is_none());
+        assert!(world.get(hunter).unwrap().energy > 100.0 + world.metabolism.energy_per_mass - 1.0);
+        // Ids still find the same cells after the ones before them went
+        assert_eq!(world.get(last).unwrap().id, last);
+        assert_eq!(world.index_of(last), Some(1));
+    }
+
+    #[test]
+    fn well_fed_cells_split_their_energy_with_a_child() {
+        let mut world = World::new();
+        world.metabolism.enabled = false;
+        let mut parent = cell(0.0, 0.0);
+        parent.energy = 200.0;
+        let parent = world.spawn(parent);
+        world.spawn(cell(0.5, 0.5));
+
+        world.step(0.01);
+        assert_eq!(world.len(), 3);
+        let child = &world.objects()[2];
+        let parent = world.get(parent).unwrap();
+        assert_eq!(parent.energy, 100.0);
+        assert_eq!(child.energy, 100.0);
+        assert_eq!(child.parent, Some(parent.id));
+        assert_eq!(child.lineage, parent.lineage);
+        assert_eq!(child.generation, 1);
+        assert_ne!(child.id, parent.id);
+        assert!(length(child.position - parent.position) > 0.0);
+    }
+
+    #[test]
+    fn open_worlds_drop_cells_past_the_cull_distance() {
+        let mut world = World::new();
+        world.boundary.mode = BoundaryMode::Open;
+        world.boundary.cull_distance = 1.0;
+        let mut leaving = cell(1.995, 0.0);
+        leaving.velocity = vec2(1.0, 0.0);
+        let leaving = world.spawn(leaving);
+        let staying = world.spawn(cell(1.5, -1.5));
+
+        world.step(0.01);
+        assert!(world.get(leaving).is_none());
+        assert!(world.get(staying).is_some());
+    }
+
+    #[test]
+    fn stationary_species_stay_put_and_removed_species_take_their_cells() {
+        let mut world = World::new();
+        let mut rock = Species::new("Rock", vec3(128.0, 128.0, 128.0));
+        rock.speed_limit = 0.0;
+        let rock = world.species.add(rock);
+        world.apply_species();
+        let still = world.spawn(world.species.create(rock, vec2(0.5, 0.5)).unwrap());
+        let food = world.spawn(world.species.create(4, vec2(-0.5, -0.5)).unwrap());
+        for _ in 0..10 {
+            world.step(0.01);
+        }
+        assert_eq!(world.get(still).unwrap().position, vec2(0.5, 0.5));
+
+        world.remove_species(0);
+        assert_eq!(world.interactions.size(), 5);
+        assert_eq!(world.get(still).unwrap().cell_type, rock - 1);
+        assert_eq!(world.get(food).unwrap().cell_type, 3);
+    }
+
+    #[test]
+    fn relationship_edits_keep_hand_set_interactions() {
+        let mut world = World::new();
+        world.interactions.set(0, 0, 0.25);
+        world.species.add(Species::new("Extra", vec3(1.0, 1.0, 1.0)));
+        world.apply_species();
+        world.set_relationship(1, 0, Relationship::Predator);
+
+        assert_eq!(world.interactions.size(), 6);
+        assert_eq!(world.interactions.get(0, 0), 0.25);
+        assert_eq!(world.interactions.get(1, 0), Relationship::Predator.attraction());
+        assert_eq!(world.interactions.get(0, 4), world.species.interactions().get(0, 4));
+    }
+
+    #[test]
+    fn scenes_carry_on_exactly_where_they_were_saved() {
+        let mut world = crowded(4, 50);
+        world.obstacles.push(Obstacle::Disc {
+            center: vec2(0.5, 0.5),
+            radius: 0.1,
+        });
+        for _ in 0..5 {
+            world.step(0.01);
+        }
+        let json = world.to_scene().to_json().unwrap();
+        let mut loaded = World::from_scene(Scene::from_json(&json).unwrap());
+        assert_eq!(loaded.obstacles, world.obstacles);
+        assert_same_steps(&mut world, &mut loaded, 20);
+
+        let id = loaded.spawn(cell(0.0, 0.0));
+        assert!(world.objects().iter().all(|object| object.id != id));
+    }
+
+    #[test]
+    fn rewinding_restores_a_snapshot_and_replays_it() {
+        let mut world = crowded(6, 50);
+        world.timeline.enabled = true;
+        world.timeline.interval = 5;
+        for _ in 0..20 {
+            world.step(0.01);
+        }
+        assert_eq!(world.timeline.len(), 4);
+        let at_ten = world.timeline.get(1).unwrap().scene().objects.clone();
+
+        assert!(world.rewind(1));
+        assert_eq!(world.steps(), 10);
+        assert!(world.objects() == &at_ten[..]);
+        assert_eq!(world.timeline.len(), 4);
+        assert!(!world.rewind(4));
+
+        // Stepping on from the snapshot drops the snapshots after it
+        for _ in 0..5 {
+            world.step(0.01);
+        }
+        assert_eq!(world.timeline.len(), 3);
+        let mut replay = World::from_scene(world.timeline.get(1).unwrap().scene().clone());
+        let original = World::from_scene(world.timeline.get(2).unwrap().scene().clone());
+        for _ in 0..5 {
+            replay.step(0.01);
+        }
+        assert!(replay.objects() == original.objects());
+    }
+
+    #[test]
+    fn generated_worlds_follow_the_seed_and_species() {
+        let mut template = cell(0.0, 0.0);
+        template.friction = 0.2;
+        let mut a = World::new();
+        a.generate(9, 40, &template);
+        let mut b = crowded(1, 10);
+        b.generate(9, 40, &template);
+
+        // Ids keep counting up, but the cells themselves are the same
+        let layout = |world: &World| -> Vec<_> {
+            world.objects().iter().map(|object| (object.cell_type, object.position)).collect()
+        };
+        assert_eq!(a.len(), 40);
+        assert_eq!(layout(&a), layout(&b));
+        for object in a.objects() {
+            let species = a.species.get(object.cell_type).unwrap();
+            assert_eq!((object.radius, object.color), (species.radius, species.color));
+            assert_eq!(object.friction, 0.2);
+        }
+    }
+
+    #[test]
+    fn contact_restitution_decides_how_cells_bounce() {
+        let world = World::new();
+        let bounce = |restitution: f32| {
+            let collisions = Collisions {
+                restitution_override: Some(restitution),
+                friction_override: Some(0.0),
+                ..Collisions::default()
+            };
+            let rules = ContactRules {
+                metabolism: &world.metabolism,
+                boundary: &world.boundary,
+                species: &world.species,
+                collisions: &collisions,
+            };
+            let mut objects = vec![cell(0.0, 0.0), cell(0.015, 0.0)];
+            objects[0].velocity = vec2(1.0, 0.0);
+            resolve_collision(&mut objects, 0, 1, &rules, &mut EventCounters::default(), &mut Vec::new());
+            (objects[0].velocity.x, objects[1].velocity.x)
+        };
+
+        let (a, b) = bounce(1.0);
+        assert!(a.abs() < 1e-6 && (b - 1.0).abs() < 1e-6, "{} {}", a, b);
+        let (a, b) = bounce(0.0);
+        assert!((a - 0.5).abs() < 1e-6 && (b - 0.5).abs() < 1e-6, "{} {}", a, b);
+    }
+
+    #[test]
+    fn fast_cells_do_not_pass_through_thin_ones() {
+        let mut world = World::new();
+        world.collisions.continuous = true;
+        let mut fast = cell(-0.1, 0.0);
+        fast.velocity = vec2(100.0, 0.0);
+        fast.speed_limit = 1000.0;
+        fast.friction = 0.0;
+        let fast = world.spawn(fast);
+        let thin = world.spawn(Circle::new(1, vec2(0.0, 0.0), 0.002, vec3(1.0, 1.0, 1.0), 1.0));
+
+        world.step(0.01);
+        let (fast, thin) = (world.get(fast).unwrap(), world.get(thin).unwrap());
+        assert!(fast.position.x < thin.position.x, "{:?} passed {:?}", fast.position, thin.position);
+        assert!(thin.velocity.x > 0.0);
+    }
+
+    #[test]
+    fn sweeping_leaves_cells_that_touch_nothing_where_they_were_going() {
+        let mut swept = World::new();
+        for (x, y) in [(-0.5, -0.5), (0.5, -0.5), (0.0, 0.5)] {
+            let mut moving = cell(x, y);
+            moving.velocity = vec2(0.3, 0.7);
+            swept.spawn(moving);
+        }
+        let mut plain = swept.clone();
+        swept.collisions.continuous = true;
+        assert_same_steps(&mut swept, &mut plain, 10);
+    }
+
+    #[test]
+    fn bonds_snap_when_pulled_too_far() {
+        let mut world = World::new();
+        let a = world.spawn(cell(0.0, 0.0));
+        let b = world.spawn(cell(0.02, 0.0));
+        assert!(world.bond(a, b));
+        assert!(!world.bond(b, a));
+        world.step(0.01);
+        assert_eq!(world.bonds.len(), 1);
+
+        let break_force = world.bonds[0].break_force;
+        let stiffness = world.bonds[0].stiffness;
+        let index = world.index_of(b).unwrap();
+        world.objects[index].position.x = 0.02 + 2.0 * break_force / stiffness;
+        world.step(0.01);
+        assert!(world.bonds.is_empty());
+    }
+
+    #[test]
+    fn cells_are_bonded_and_unbonded_by_id() {
+        let mut world = World::new();
+        let a = world.spawn(cell(0.0, 0.0));
+        let b = world.spawn(cell(0.3, 0.0));
+        assert!(!world.bond(a, a));
+        assert!(!world.bond(a, EntityId(99)));
+        assert!(world.bond(a, b));
+        assert!((world.bonds[0].rest_length - 0.3).abs() < 1e-6);
+
+        assert!(world.unbond(b, a));
+        assert!(!world.unbond(a, b));
+        assert!(world.bonds.is_empty());
+    }
+
+    #[test]
+    fn emitters_spawn_at_their_rate_and_sinks_take_cells_out() {
+        let mut world = World::new();
+        let mut emitter = Emitter::new(4, vec2(-0.5, 0.0));
+        emitter.rate = 25.0;
+        world.emitters.push(emitter);
+        for _ in 0..10 {
+            world.step(0.02);
+        }
+        // Half a cell is due every step
+        assert_eq!(world.events.emitted, 5);
+        assert_eq!(world.len(), 5);
+        assert!(world.objects().iter().all(|object| object.cell_type == 4));
+
+        world.emitters.clear();
+        world.sinks.push(Sink {
+            position: vec2(-0.5, 0.0),
+            radius: 0.2,
+        });
+        let outside = world.spawn(cell(0.5, 0.5));
+        world.step(0.02);
+        assert_eq!(world.events.absorbed, 5);
+        assert_eq!(world.len(), 1);
+        assert!(world.get(outside).is_some());
+
+        // Emitters follow their species when an earlier one is removed
+        world.emitters.push(Emitter::new(0, vec2(0.0, 0.0)));
+        world.emitters.push(Emitter::new(4, vec2(0.0, 0.0)));
+        world.remove_species(0);
+        assert_eq!(world.emitters.len(), 1);
+        assert_eq!(world.emitters[0].cell_type, 3);
+    }
+
+    #[test]
+    fn emitters_keep_to_the_step_and_population_limits() {
+        let mut world = World::new();
+        let mut emitter = Emitter::new(4, vec2(0.0, 0.0));
+        emitter.rate = 1e6;
+        world.emitters.push(emitter);
+        world.reproduction.max_population = 100;
+        world.step(0.05);
+        assert_eq!(world.len(), MAX_EMITTED_PER_STEP);
+        world.step(0.05);
+        assert_eq!(world.len(), 100);
+    }
+
+    #[test]
+    fn registered_behaviours_steer_their_species_and_see_as_far_as_they_ask() {
+        // Pushes right by one for every cell it can see
+        struct Counter(f32);
+        impl Behavior for Counter {
+            fn perception(&self) -> f32 {
+                self.0
+            }
+
+            fn steer(&self, view: &Neighbourhood, _rng: &mut dyn RngCore) -> Option<Vec2> {
+                Some(vec2(view.neighbours.len() as f32, 0.0))
+            }
+        }
+
+        for broad_phase in [BroadPhase::BruteForce, BroadPhase::SpatialHash] {
+            let mut world = World::new();
+            world.broad_phase = broad_phase;
+            world.behaviors.register("far", Counter(0.8));
+            world.behaviors.register("near", Counter(0.0));
+            world.species.get_mut(0).unwrap().behavior = BehaviorKind::Custom("far".to_string());
+            world.species.get_mut(1).unwrap().behavior = BehaviorKind::Custom("near".to_string());
+            // Further apart than the force curve reaches
+            let a = world.spawn(world.species.create(0, vec2(-0.35, 0.0)).unwrap());
+            let b = world.spawn(world.species.create(0, vec2(0.35, 0.0)).unwrap());
+            // Only the far-sighted species sees this far
+            let c = world.spawn(world.species.create(1, vec2(0.35, 0.6)).unwrap());
+            world.step(0.01);
+            for id in [a, b] {
+                assert!(world.get(id).unwrap().velocity.x > 0.0, "{:?}", broad_phase);
+            }
+            assert_eq!(world.get(c).unwrap().velocity.x, 0.0, "{:?}", broad_phase);
+        }
+    }
+    #[test]
+    fn the_inspector_shows_what_steered_the_cell() {
+        let mut world = World::new();
+        world.reproduction.enabled = false;
+        world.species.get_mut(0).unwrap().behavior = BehaviorKind::Neural(Neural::default());
+        // A species that sees further must not widen this one's view
+        world.species.get_mut(1).unwrap().behavior = BehaviorKind::Boids(Boids {
+            radius: 0.9,
+            ..Boids::default()
+        });
+        let mut thinker = world.species.create(0, vec2(0.0, 0.0)).unwrap();
+        thinker.velocity = vec2(0.01, 0.0);
+        thinker.mass = 1.0;
+        thinker.friction = 0.0;
+        thinker.speed_limit = f32::INFINITY;
+        let id = world.spawn(thinker);
+        world.spawn(world.species.create(4, vec2(0.2, 0.1)).unwrap());
+        world.spawn(world.species.create(1, vec2(-0.7, 0.0)).unwrap());
+        world.grow_brains(0, 6);
+
+        let [thrust, turn] = world.think(id).unwrap().outputs;
+        let before = world.get(id).unwrap().velocity;
+        let dt = 0.01;
+        world.step(dt);
+        // Heading along +x, so thrust pushes along x and turning along y
+        let push = (world.get(id).unwrap().velocity - before) / (dt * Neural::default().strength);
+        assert!((push.x - thrust).abs() < 1e-4, "{} vs {}", push.x, thrust);
+        assert!((push.y - turn).abs() < 1e-4, "{} vs {}", push.y, turn);
+    }
+}